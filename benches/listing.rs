@@ -0,0 +1,54 @@
+//! Measures how long it takes to fill a grid with a large file listing and
+//! fit it into a terminal, for listings of plain ASCII names, which skip
+//! the Unicode width tables, and for listings of names that need them.
+//! The mixed listing has names of many different widths, in no order, so
+//! adding them can’t get away with only ever seeing the same width.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rc_column::{Alignment, Cell, Direction, Filling, Grid, GridOptions};
+use unicode_width::UnicodeWidthStr;
+
+const NAMES: usize = 100_000;
+
+fn ascii_names() -> Vec<String> {
+    (0 .. NAMES).map(|i| format!("file_{:06}.rs", i)).collect()
+}
+
+fn unicode_names() -> Vec<String> {
+    (0 .. NAMES).map(|i| format!("ファイル_{:06}.rs", i)).collect()
+}
+
+fn mixed_names() -> Vec<String> {
+    (0 .. NAMES).map(|i| format!("{}_{}.rs", "f".repeat((i * 7919) % 40 + 1), i)).collect()
+}
+
+fn measuring(c: &mut Criterion) {
+    let names = ascii_names();
+
+    c.bench_function("make ascii cells using the unicode tables", |b| {
+        b.iter(|| names.iter().map(|name| Cell::raw(name.clone(), UnicodeWidthStr::width(&name[..]), Alignment::Left).width).sum::<usize>())
+    });
+
+    c.bench_function("make ascii cells", |b| {
+        b.iter(|| names.iter().map(|name| Cell::from(&name[..]).width).sum::<usize>())
+    });
+}
+
+fn listing(c: &mut Criterion) {
+    for (label, names) in [ ("ascii", ascii_names()), ("unicode", unicode_names()), ("mixed", mixed_names()) ] {
+        c.bench_function(&format!("fit {} listing", label), |b| {
+            b.iter(|| {
+                let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+                grid.reserve(names.len());
+                for name in &names {
+                    grid.add(Cell::from(&name[..]));
+                }
+
+                black_box(grid.fit_into_width(200).map(|display| display.row_count()))
+            })
+        });
+    }
+}
+
+criterion_group!(benches, measuring, listing);
+criterion_main!(benches);
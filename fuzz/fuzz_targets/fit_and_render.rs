@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rc_column::Grid;
+
+use std::fmt::Write;
+
+// The first byte is the width to fit the grid into, and the rest builds it.
+fuzz_target!(|data: &[u8]| {
+    let Some((&width, input)) = data.split_first() else { return };
+    let grid = Grid::from_fuzz_input(input);
+
+    if let Some(display) = grid.fit_into_width(width.into()) {
+        let mut rendered = String::new();
+        let _ = write!(rendered, "{}", display);
+    }
+});
@@ -0,0 +1,149 @@
+//! Formats the lines read from standard input into columns, like
+//! `column(1)`, using nothing but the library’s public API.
+//!
+//! The width is taken from `--width`, or the `COLUMNS` environment
+//! variable, or else 80. Lines that won’t fit into that many columns side
+//! by side are written out one per line, and tables that won’t fit are
+//! written out as wide as they need to be.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use rc_column::{Cell, Direction, Filling, Grid, GridOptions, Width};
+
+const USAGE: &str = "\
+usage: rc_column [options] < input
+
+options:
+  -w, --width N          fit the columns into N characters
+  -x, --across           fill rows before columns
+  -s, --separator TEXT   put TEXT between columns instead of two spaces
+  -t, --table            split lines into fields and line them up
+  -d, --delimiters CHARS with -t, split fields on any of CHARS instead of
+                         whitespace
+  -h, --help             show this message";
+
+struct Args {
+    width: Width,
+    direction: Direction,
+    filling: Filling,
+    table: bool,
+    delimiters: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        width: env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()).unwrap_or(80),
+        direction: Direction::TopToBottom,
+        filling: Filling::Spaces(2),
+        table: false,
+        delimiters: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match &arg[..] {
+            "-w" | "--width"       => parsed.width = value()?.parse().map_err(|_| "the width must be a number".to_owned())?,
+            "-x" | "--across"      => parsed.direction = Direction::LeftToRight,
+            "-s" | "--separator"   => parsed.filling = Filling::Text(value()?),
+            "-t" | "--table"       => parsed.table = true,
+            "-d" | "--delimiters"  => parsed.delimiters = Some(value()?),
+            "-h" | "--help"        => return Ok(None),
+            _                      => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    if parsed.delimiters.is_some() && !parsed.table {
+        return Err("--delimiters only works with --table".to_owned());
+    }
+
+    Ok(Some(parsed))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args))  => args,
+        Ok(None)        => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        },
+        Err(message)    => {
+            eprintln!("rc_column: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        },
+    };
+
+    let lines = match io::stdin().lock().lines().collect::<io::Result<Vec<String>>>() {
+        Ok(lines)   => lines,
+        Err(error)  => {
+            eprintln!("rc_column: {}", error);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let options = GridOptions::new(args.filling, args.direction);
+    let grid = if args.table {
+        Grid::from_lines(options, lines.iter().map(String::as_str), args.delimiters.as_deref())
+    }
+    else {
+        let mut grid = Grid::new(options);
+        grid.reserve(lines.len());
+        for line in lines.iter().filter(|line| !line.is_empty()) {
+            grid.add(Cell::from(&line[..]));
+        }
+        grid
+    };
+
+    // A table that doesn’t fit still gets its fields lined up, running
+    // past the edge of the width rather than losing its columns.
+    let display = grid.fit_into_width(args.width)
+                      .or_else(|| grid.fit_into_width(Width::MAX).filter(|_| args.table));
+
+    let rendered = match display {
+        Some(display)  => display.to_string(),
+        None           => lines.iter().map(|line| format!("{}\n", line)).collect(),
+    };
+
+    match io::stdout().lock().write_all(rendered.as_bytes()) {
+        Ok(())                                                  => ExitCode::SUCCESS,
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe  => ExitCode::SUCCESS,
+        Err(error)                                              => {
+            eprintln!("rc_column: {}", error);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options() {
+        let args = parse(&[ "-w", "40", "-x", "-s", " | " ]).unwrap().unwrap();
+        assert_eq!(args.width, 40);
+        assert_eq!(args.direction, Direction::LeftToRight);
+        assert_eq!(args.filling, Filling::Text(" | ".into()));
+        assert!(!args.table);
+
+        let args = parse(&[ "--table", "--delimiters", ",:" ]).unwrap().unwrap();
+        assert!(args.table);
+        assert_eq!(args.delimiters.as_deref(), Some(",:"));
+
+        assert!(parse(&[ "--help" ]).unwrap().is_none());
+    }
+
+    #[test]
+    fn bad_options() {
+        assert_eq!(parse(&[ "-w" ]).err().unwrap(), "-w needs a value");
+        assert_eq!(parse(&[ "-w", "wide" ]).err().unwrap(), "the width must be a number");
+        assert_eq!(parse(&[ "--frobnicate" ]).err().unwrap(), "unknown option --frobnicate");
+        assert_eq!(parse(&[ "-d", "," ]).err().unwrap(), "--delimiters only works with --table");
+    }
+}
@@ -0,0 +1,116 @@
+//! A C interface, for programs not written in Rust that want to lay out
+//! grids the same way, whether from C or from another language through its
+//! foreign function interface.
+//!
+//! Build a shared library to link against with
+//! `cargo rustc --release --features ffi --crate-type cdylib`. Grids and
+//! rendered strings are allocated by this library, and have to be given back
+//! to it to be freed.
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use super::{Direction, Filling, Grid, GridOptions, Width};
+
+
+/// Creates an empty grid with the given number of spaces between columns,
+/// filled from top to bottom if `top_to_bottom` is true, or from left to
+/// right otherwise. Free it with `rc_column_grid_free`.
+#[no_mangle]
+pub extern "C" fn rc_column_grid_new(spaces: Width, top_to_bottom: bool) -> *mut Grid {
+    let direction = if top_to_bottom { Direction::TopToBottom } else { Direction::LeftToRight };
+    let grid = Grid::new(GridOptions::new(Filling::Spaces(spaces), direction));
+    Box::into_raw(Box::new(grid))
+}
+
+/// Adds a cell to the end of the grid. Returns false, leaving the grid as it
+/// was, if either pointer is null or the contents aren’t valid UTF-8.
+///
+/// # Safety
+///
+/// `grid` has to have come from `rc_column_grid_new` and not been freed, and
+/// `contents` has to point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rc_column_grid_add(grid: *mut Grid, contents: *const c_char) -> bool {
+    if grid.is_null() || contents.is_null() {
+        return false;
+    }
+
+    match CStr::from_ptr(contents).to_str() {
+        Ok(contents) => {
+            (*grid).add(contents.into());
+            true
+        },
+        Err(_) => false,
+    }
+}
+
+/// Fits the grid into the given width and renders it, returning a
+/// nul-terminated string with one line of the grid per line of text. Returns
+/// null if the grid doesn’t fit, or if `grid` is null. Free the string with
+/// `rc_column_string_free`.
+///
+/// # Safety
+///
+/// `grid` has to have come from `rc_column_grid_new` and not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn rc_column_grid_fit(grid: *const Grid, maximum_width: Width) -> *mut c_char {
+    let Some(grid) = grid.as_ref() else { return ptr::null_mut() };
+
+    grid.fit_into_width(maximum_width)
+        .and_then(|display| CString::new(display.to_string()).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Frees a grid. Does nothing if `grid` is null.
+///
+/// # Safety
+///
+/// `grid` has to have come from `rc_column_grid_new` and not been freed
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn rc_column_grid_free(grid: *mut Grid) {
+    if !grid.is_null() {
+        drop(Box::from_raw(grid));
+    }
+}
+
+/// Frees a string returned by `rc_column_grid_fit`. Does nothing if `string`
+/// is null.
+///
+/// # Safety
+///
+/// `string` has to have come from `rc_column_grid_fit` and not been freed
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn rc_column_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        unsafe {
+            let grid = rc_column_grid_new(2, false);
+            for s in [c"one", c"two", c"three"] {
+                assert!(rc_column_grid_add(grid, s.as_ptr()));
+            }
+
+            assert!(!rc_column_grid_add(grid, c"\xff".as_ptr()));
+            assert!(!rc_column_grid_add(ptr::null_mut(), c"four".as_ptr()));
+
+            let rendered = rc_column_grid_fit(grid, 80);
+            assert_eq!(CStr::from_ptr(rendered).to_str(), Ok("one  two  three\n"));
+            rc_column_string_free(rendered);
+
+            assert!(rc_column_grid_fit(grid, 2).is_null());
+            rc_column_grid_free(grid);
+        }
+    }
+}
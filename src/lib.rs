@@ -1,29 +1,115 @@
-use std::cmp::max;
+use std::borrow::Cow;
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
 use std::fmt;
-use std::iter::repeat;
+use std::iter::{once, repeat_n};
+use std::ops::{ControlFlow, Range};
+use std::sync::Arc;
+use std::time::Duration;
 
 extern crate unicode_width;
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+mod table;
+pub use table::{SortOrder, Table, TableDisplay};
+
+mod sync;
+pub use sync::SyncGrid;
+
+#[cfg(feature = "ratatui")]
+mod widget;
+#[cfg(feature = "ratatui")]
+pub use widget::GridWidget;
+
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmGrid;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "python")]
+mod python;
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[non_exhaustive]
 pub enum Alignment {
     Left,
     Right,
+
+    /// Split the padding between both sides, putting any odd space on the
+    /// right.
+    Center,
+}
+
+/// Where a cell goes within a row that is taller than it is, which happens
+/// when another cell in the row wraps onto more lines.
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
+#[non_exhaustive]
+pub enum VerticalAlignment {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// The string type holding the contents of each cell. With the
+/// `compact_str` feature, this is a `CompactString`, which keeps strings of
+/// up to 24 bytes inline instead of allocating them, so grids of millions
+/// of short names make far fewer allocations. It isn’t part of the public
+/// API, which only ever hands out a `str`, so the feature can be turned on
+/// without breaking anything.
+#[cfg(not(feature = "compact_str"))]
+type Contents = String;
+
+/// The string type holding the contents of each cell, which keeps strings
+/// of up to 24 bytes inline instead of allocating them.
+#[cfg(feature = "compact_str")]
+type Contents = compact_str::CompactString;
+
+/// Turns a string into cell contents, which costs nothing unless the
+/// contents are a `CompactString`.
+#[allow(clippy::useless_conversion)]
+fn into_contents(string: String) -> Contents {
+    string.into()
+}
+
+/// The number of bytes of heap memory allocated for some cell contents.
+#[cfg(not(feature = "compact_str"))]
+fn heap_size(contents: &Contents) -> usize {
+    contents.capacity()
+}
+
+/// The number of bytes of heap memory allocated for some cell contents,
+/// which is none for contents short enough to be kept inline.
+#[cfg(feature = "compact_str")]
+fn heap_size(contents: &Contents) -> usize {
+    if contents.is_heap_allocated() { contents.capacity() } else { 0 }
 }
 
 #[derive(PartialEq, Debug, Clone)]
+#[non_exhaustive]
 pub struct Cell {
-    pub contents: String,
+    contents: Contents,
     pub width: usize,
     pub alignment: Alignment,
+    pub vertical_alignment: VerticalAlignment,
+
+    /// The character used to pad the cell out to the width of its column,
+    /// such as `0` for numbers or `.` for leaders. It’s taken to be one
+    /// column wide.
+    pub pad_char: char,
 }
 
 impl From<String> for Cell {
     fn from(string: String) -> Self {
         Self {
-            width: UnicodeWidthStr::width(&*string),
-            contents: string,
+            width: measure(&string),
+            contents: into_contents(string),
             alignment: Alignment::Left,
+            vertical_alignment: VerticalAlignment::Top,
+            pad_char: ' ',
         }
     }
 }
@@ -31,14 +117,337 @@ impl From<String> for Cell {
 impl<'a> From<&'a str> for Cell {
     fn from(string: &'a str) -> Self {
         Self {
-            width: UnicodeWidthStr::width(&*string),
+            width: measure(string),
             contents: string.into(),
             alignment: Alignment::Left,
+            vertical_alignment: VerticalAlignment::Top,
+            pad_char: ' ',
+        }
+    }
+}
+
+impl Cell {
+
+    /// Makes a cell whose width has already been measured, such as by a
+    /// renderer that knows how wide its glyphs are, without measuring it
+    /// again. The width is trusted as it is, so getting it wrong throws off
+    /// the alignment of everything after the cell.
+    pub fn raw(contents: String, width: Width, alignment: Alignment) -> Self {
+        Self {
+            contents: into_contents(contents),
+            width,
+            alignment,
+            vertical_alignment: VerticalAlignment::Top,
+            pad_char: ' ',
+        }
+    }
+
+    /// The text shown in the cell.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Replaces the text shown in the cell. The width is measured again
+    /// afterwards, so it matches what gets displayed.
+    pub fn set_contents(&mut self, contents: String) {
+        self.width = measure(&contents);
+        self.contents = into_contents(contents);
+    }
+
+    /// Sets how the cell is aligned within its column.
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Sets where the cell goes within a row that is taller than it is.
+    pub fn vertical_alignment(mut self, vertical_alignment: VerticalAlignment) -> Self {
+        self.vertical_alignment = vertical_alignment;
+        self
+    }
+
+    /// Sets the character the cell gets padded out to its column’s width
+    /// with.
+    pub fn pad_char(mut self, pad_char: char) -> Self {
+        self.pad_char = pad_char;
+        self
+    }
+
+    /// Replaces any control characters in the cell’s contents with visible
+    /// stand-ins, so names containing carriage returns, bells, or escape
+    /// sequences can’t mangle the rest of the grid. C0 control characters
+    /// and DEL are shown in caret notation (`^M`, `^?`), and C1 control
+    /// characters are shown as U+FFFD. The width is measured again
+    /// afterwards, so it matches what gets displayed.
+    pub fn sanitize(mut self) -> Self {
+        if !self.contents.chars().any(char::is_control) {
+            return self;
+        }
+
+        let mut sanitized = String::with_capacity(self.contents.len());
+        for c in self.contents.chars() {
+            match c {
+                '\0' ..= '\x1F' => {
+                    sanitized.push('^');
+                    sanitized.push(char::from(c as u8 + b'@'));
+                },
+                '\x7F' => sanitized.push_str("^?"),
+                c if c.is_control() => sanitized.push('\u{FFFD}'),
+                c => sanitized.push(c),
+            }
+        }
+
+        self.contents = into_contents(sanitized);
+        self.width = measure(&self.contents);
+        self
+    }
+
+    /// Converts the cell’s contents to Unicode Normalization Form C, so
+    /// strings made of decomposed characters, such as file names that come
+    /// from macOS, get measured and displayed the same way as their composed
+    /// equivalents. The width is measured again afterwards.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfc(mut self) -> Self {
+        use unicode_normalization::UnicodeNormalization;
+
+        self.contents = self.contents.nfc().collect();
+        self.width = measure(&self.contents);
+        self
+    }
+
+    /// Makes a right-aligned cell showing a whole number, with its digits
+    /// grouped into thousands if the format asks for it, such as
+    /// `1,234,567` or `1 234 567`.
+    pub fn from_number(number: impl Into<i128>, format: NumberFormat) -> Self {
+        let number = number.into();
+        let digits = number.unsigned_abs().to_string();
+
+        let mut contents = String::with_capacity(digits.len() * 2);
+        if number < 0 {
+            contents.push('-');
+        }
+
+        for (index, digit) in digits.chars().enumerate() {
+            let remaining = digits.len() - index;
+            if let NumberFormat::Grouped(separator) = format {
+                if index > 0 && remaining % 3 == 0 {
+                    contents.push(separator);
+                }
+            }
+
+            contents.push(digit);
+        }
+
+        let mut cell = Cell::from(contents);
+        cell.alignment = Alignment::Right;
+        cell
+    }
+
+    /// Makes a right-aligned cell showing a number of bytes in the largest
+    /// unit that keeps the number at least 1, to one decimal place, such as
+    /// `4.2 KiB` or `4.2 kB`. Sizes under a kilobyte are shown in bytes,
+    /// with no decimal places. The unit is padded to the width of the
+    /// widest unit, so the numbers in a column of sizes line up.
+    pub fn from_bytes(bytes: u64, units: BinaryOrDecimal) -> Self {
+        let (base, names, unit_width) = match units {
+            BinaryOrDecimal::Binary   => (1024.0, [ "B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB" ], 3),
+            BinaryOrDecimal::Decimal  => (1000.0, [ "B", "kB", "MB", "GB", "TB", "PB", "EB" ], 2),
+        };
+
+        let mut value = bytes as f64;
+        let mut unit = 0;
+
+        // Move up a unit whenever rounding would show a whole unit’s worth.
+        while unit + 1 < names.len() && (value * 10.0).round() / 10.0 >= base {
+            value /= base;
+            unit += 1;
+        }
+
+        let contents = if unit == 0 {
+            format!("{} {:<width$}", bytes, names[0], width = unit_width)
+        }
+        else {
+            format!("{:.1} {:<width$}", value, names[unit], width = unit_width)
+        };
+
+        let mut cell = Cell::from(contents);
+        cell.alignment = Alignment::Right;
+        cell
+    }
+
+    /// Makes a right-aligned cell showing a duration as hours, minutes and
+    /// seconds, such as `01:02:03`. Every field is padded to two digits, so
+    /// durations under a hundred hours are all the same width; anything
+    /// shorter than a second shows as `00:00:00`.
+    pub fn from_duration(duration: Duration) -> Self {
+        let seconds = duration.as_secs();
+        let contents = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+        let mut cell = Cell::from(contents);
+        cell.alignment = Alignment::Right;
+        cell
+    }
+
+    /// Makes a cell showing a point in time in the local time zone,
+    /// formatted with a `chrono` format string such as `"%Y-%m-%d %H:%M"`.
+    /// Numeric fields in chrono’s formats are zero-padded, so a format
+    /// without names of days or months gives cells of the same width. The
+    /// cell is aligned to the right, like a duration.
+    #[cfg(feature = "chrono")]
+    pub fn from_timestamp(time: std::time::SystemTime, format: &str) -> Self {
+        let time: chrono::DateTime<chrono::Local> = time.into();
+
+        let mut cell = Cell::from(time.format(format).to_string());
+        cell.alignment = Alignment::Right;
+        cell
+    }
+}
+
+/// Which multiples of bytes to use, for `Cell::from_bytes`.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum BinaryOrDecimal {
+
+    /// Powers of 1024, with units such as KiB and MiB.
+    Binary,
+
+    /// Powers of 1000, with units such as kB and MB.
+    Decimal,
+}
+
+/// How to write out the digits of a number, for `Cell::from_number`.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum NumberFormat {
+
+    /// Write the digits one after another.
+    Plain,
+
+    /// Separate each group of three digits with the given character, such
+    /// as `,` in English, `.` in German, or a space in French.
+    Grouped(char),
+}
+
+/// What to do with a cell whose contents are wider than the column it has
+/// been given.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum Overflow {
+    /// Cut the contents short, ending them with an ellipsis.
+    Truncate,
+
+    /// Break the contents across as many lines as they need.
+    Wrap,
+}
+
+/// Which part of a cell’s contents gets cut out when it’s truncated, and so
+/// where the ellipsis goes.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+#[non_exhaustive]
+pub enum TruncateSide {
+
+    /// Keep the end, such as the name of a file at the end of a long path.
+    Left,
+
+    /// Keep the start and the end, cutting out the middle.
+    Middle,
+
+    /// Keep the start. This is the default.
+    #[default]
+    Right,
+}
+
+/// How wide to take emoji sequences to be. Sequences such as `👨‍👩‍👧` are
+/// emoji joined by zero-width joiners, and `❤️` is a character followed by a
+/// variation selector, and terminals don’t agree on how many columns they
+/// take up.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+#[non_exhaustive]
+pub enum EmojiWidth {
+
+    /// Use the widths from the Unicode tables, as measured when the cell
+    /// was made. This is the default.
+    #[default]
+    Measured,
+
+    /// Take every emoji sequence to be two columns wide, which is how most
+    /// terminals that draw them as a single glyph display them.
+    Wide,
+}
+
+impl EmojiWidth {
+
+    /// Measures a string according to this policy.
+    pub fn width(self, string: &str) -> Width {
+        match self {
+            EmojiWidth::Measured  => measure(string),
+            EmojiWidth::Wide      => clusters(string, self).map(|(_, width)| width).sum(),
         }
     }
 }
 
+/// Splits a string into the pieces that can’t be broken apart when it’s
+/// truncated or wrapped, along with their widths. These are single
+/// characters, except that emoji sequences are kept together when the
+/// policy takes them to be two columns wide.
+fn clusters(string: &str, policy: EmojiWidth) -> impl Iterator<Item = (&str, Width)> {
+    let mut rest = string;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut end = first.len_utf8();
+
+        if policy == EmojiWidth::Wide {
+            let mut joined = false;
+            for (index, c) in chars {
+                if joined || is_emoji_modifier(c) {
+                    joined = false;
+                }
+                else if c == '\u{200D}' {
+                    joined = true;
+                }
+                else {
+                    break;
+                }
+                end = index + c.len_utf8();
+            }
+        }
+
+        let (cluster, remainder) = rest.split_at(end);
+        rest = remainder;
+
+        // A lone text presentation selector asks for the character to be
+        // drawn as it normally would be, so it keeps its usual width.
+        if cluster.len() > first.len_utf8() && !cluster.ends_with('\u{FE0E}') {
+            Some((cluster, 2))
+        }
+        else {
+            Some((cluster, UnicodeWidthChar::width(first).unwrap_or(0)))
+        }
+    })
+}
+
+/// Measures a string using the Unicode tables, skipping them for strings
+/// made up of printable ASCII characters, which are one column wide each.
+/// Most cells, such as file names, are like this.
+fn measure(string: &str) -> Width {
+    if string.bytes().all(|b| matches!(b, b' ' ..= b'~')) {
+        string.len()
+    }
+    else {
+        UnicodeWidthStr::width(string)
+    }
+}
+
+/// Whether the character changes how the one before it is displayed,
+/// rather than being displayed itself: variation selectors, skin tone
+/// modifiers, the enclosing keycap, and tag characters.
+fn is_emoji_modifier(c: char) -> bool {
+    matches!(c, '\u{FE0E}' | '\u{FE0F}' | '\u{20E3}' | '\u{1F3FB}' ..= '\u{1F3FF}' | '\u{E0020}' ..= '\u{E007F}')
+}
+
 #[derive(PartialEq, Debug, Copy, Clone)]
+#[non_exhaustive]
 pub enum Direction {
     LeftToRight,
     TopToBottom,
@@ -46,14 +455,27 @@ pub enum Direction {
 
 pub type Width = usize;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
+#[non_exhaustive]
 pub enum Filling {
     Spaces(Width),
     Text(String),
 }
 
 impl Filling {
-    fn width(&self) -> Width {
+
+    /// Separates columns with the given number of spaces.
+    pub fn spaces(width: Width) -> Self {
+        Filling::Spaces(width)
+    }
+
+    /// Separates columns with the given text, such as `" | "`.
+    pub fn text(text: impl Into<String>) -> Self {
+        Filling::Text(text.into())
+    }
+
+    /// The number of columns the separator takes up.
+    pub fn width(&self) -> Width {
         match *self {
             Filling::Spaces(width) => width,
             // ref text 是一个模式，它匹配 Text 分支，并将其内部的字符串引用绑定到变量 text。
@@ -62,15 +484,27 @@ impl Filling {
     }
 }
 
-#[derive(PartialEq, Debug)]
-struct Dimensions {
-    num_lines: Width,
+/// The shape a grid gets laid out in: how many lines it takes up, and how
+/// wide each of its columns is.
+///
+/// A layout can be kept and used to display the grid again later, or to
+/// display another grid with the same number of cells, without having to
+/// fit it into a width again.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Layout {
 
-    widths: Vec<Width>
+    /// The number of lines the cells are spread over.
+    pub num_lines: Width,
+
+    /// The width of each column, from left to right.
+    pub widths: Vec<Width>
 }
 
-impl Dimensions {
-    fn total_width(&self, separator_width: Width) -> Width {
+impl Layout {
+
+    /// The total width of the columns, with the given separator width
+    /// between each pair.
+    pub fn total_width(&self, separator_width: Width) -> Width {
         if self.widths.is_empty() {
            0
         }
@@ -81,439 +515,5417 @@ impl Dimensions {
             values + separators
         }
     }
+
+    /// Encodes the layout in a few bytes, so it can be saved and given to
+    /// `Display::from_layout` when the same cells come round again, such as
+    /// on the next run of a command under `watch`, without fitting them
+    /// into a width again. The bytes are a version number followed by the
+    /// number of lines, the number of columns, and the width of each
+    /// column, each as an unsigned LEB128 number.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![ 1 ];
+        for number in [ self.num_lines, self.widths.len() ].into_iter().chain(self.widths.iter().copied()) {
+            let mut number = number as u64;
+            while number >= 0x80 {
+                bytes.push(number as u8 | 0x80);
+                number >>= 7;
+            }
+            bytes.push(number as u8);
+        }
+
+        bytes
+    }
+
+    /// Decodes a layout encoded with `to_bytes`, or returns `None` if the
+    /// bytes aren’t one, such as if they’ve been cut short.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&1, mut bytes) = bytes.split_first()? else {
+            return None;
+        };
+
+        let mut next = || {
+            let mut number: u64 = 0;
+            for shift in (0 .. 64).step_by(7) {
+                let (&byte, rest) = bytes.split_first()?;
+                bytes = rest;
+                number |= u64::from(byte & 0x7F).checked_shl(shift)?;
+                if byte & 0x80 == 0 {
+                    return Width::try_from(number).ok();
+                }
+            }
+            None
+        };
+
+        let num_lines = next()?;
+        let num_columns = next()?;
+        let widths = (0 .. num_columns).map(|_| next()).collect::<Option<Vec<_>>>()?;
+
+        if bytes.is_empty() { Some(Self { num_lines, widths }) } else { None }
+    }
 }
 
-#[derive(PartialEq, Debug)]
+/// A function supplied by the caller to customise how a grid behaves.
+/// Functions can’t be compared, so two callbacks are only equal if they are
+/// clones of each other.
+pub struct Callback<F: ?Sized>(Arc<F>);
+
+impl<F: ?Sized> Clone for Callback<F> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<F: ?Sized> PartialEq for Callback<F> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback(..)")
+    }
+}
+
+/// How to choose between the layouts that fit into a width. Each strategy
+/// gives every layout a score, and the layout with the lowest score wins;
+/// ties go to the layout with fewer lines.
+#[derive(PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum LayoutScore {
+
+    /// Use as few lines as possible. This is the default.
+    FewestLines,
+
+    /// Take up as little width as possible.
+    NarrowestWidth,
+
+    /// Take up as little space as possible, counting the width multiplied
+    /// by the number of lines.
+    Densest,
+
+    /// Keep the columns as close to the same height as possible, leaving as
+    /// few empty positions in the last column or row as possible.
+    BalancedColumns,
+
+    /// Score layouts using a function of their dimensions.
+    Custom(Callback<dyn Fn(&Layout) -> usize + Send + Sync>),
+}
+
+impl LayoutScore {
+
+    /// Scores layouts using the given function of their dimensions.
+    pub fn custom(score: impl Fn(&Layout) -> usize + Send + Sync + 'static) -> Self {
+        LayoutScore::Custom(Callback(Arc::new(score)))
+    }
+}
+
+/// How to choose between layouts that the layout score rates equally.
+/// Candidate layouts are always considered in order of increasing number of
+/// lines, so either way the choice is the same every time.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum TieBreak {
+
+    /// Pick the layout with the fewest lines. This is the default.
+    FirstFit,
+
+    /// Pick a layout whose last column is its widest, if there is one, so
+    /// any ragged edge ends up on the right. Otherwise, pick the layout with
+    /// the fewest lines.
+    WidestColumnLast,
+}
+
+/// Where to put the grid as a whole when it’s narrower than the width it
+/// was fitted into.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum GridAlign {
+
+    /// Start every line at the left edge. This is the default.
+    Left,
+
+    /// Indent every line so the grid sits in the middle of the width,
+    /// rounding towards the left.
+    Center,
+
+    /// Indent every line so the grid’s right edge lines up with the end of
+    /// the width.
+    Right,
+}
+
+/// Which side of the separator after a cell its padding goes on. This only
+/// shows with `Filling::Text`, as separators made of spaces look the same
+/// either way.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+#[non_exhaustive]
+pub enum PaddingPlacement {
+
+    /// Pad each cell out to the width of its column before the separator,
+    /// so the separators line up, such as `ab  |cd`. This is the default.
+    #[default]
+    BeforeSeparator,
+
+    /// Write the separator straight after each cell’s contents, and the
+    /// padding that would have followed them after it, such as `ab|  cd`.
+    /// Padding that goes before the contents of right-aligned and centred
+    /// cells stays where it is. The cells still line up, but the separators
+    /// stay next to the cells before them, as the gap does with spaces.
+    AfterSeparator,
+}
+
+/// How a grid gets written out when it’s displayed.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum OutputMode {
+
+    /// Lay the cells out in aligned columns. This is the default.
+    Grid,
+
+    /// Write each cell on a line of its own, in reading order, after its
+    /// row and column numbers, such as `Row 2, column 1: apple`. Nothing is
+    /// padded, truncated, wrapped, or styled, so the output reads well with
+    /// a screen reader, or for anyone who’s asked for plain output.
+    Plain,
+}
+
+/// How many spaces go between columns, depending on how many columns there
+/// are, so a grid with only a few columns can spread them out, and one with
+/// many can pack them in. This only applies to grids separated by spaces;
+/// text separators, and separators set for particular columns, stay as
+/// they are.
+#[derive(PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum Gutter {
+
+    /// Put `widest` spaces between two columns, and one fewer for each
+    /// column after that, down to `narrowest`.
+    Tapered { widest: Width, narrowest: Width },
+
+    /// Work out the number of spaces from the number of columns. Functions
+    /// that give more columns wider gaps can stop the grid from finding
+    /// layouts with fewer lines, as it takes more columns to be harder to
+    /// fit.
+    Custom(Callback<dyn Fn(usize) -> Width + Send + Sync>),
+}
+
+impl Gutter {
+
+    /// Works out the number of spaces using the given function of the
+    /// number of columns.
+    pub fn custom(width: impl Fn(usize) -> Width + Send + Sync + 'static) -> Self {
+        Gutter::Custom(Callback(Arc::new(width)))
+    }
+
+    /// The number of spaces between columns in a grid with the given number
+    /// of columns.
+    fn width(&self, num_columns: usize) -> Width {
+        match self {
+            Gutter::Tapered { widest, narrowest }  => max(widest.saturating_sub(num_columns.saturating_sub(2)), *narrowest),
+            Gutter::Custom(width)                  => (width.0)(num_columns),
+        }
+    }
+}
+
+/// Something to start each line of a rendered grid with, such as `> ` to
+/// quote it, or a line number. The grid is fitted into whatever width the
+/// prefix leaves.
+#[derive(PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum LinePrefix {
+
+    /// Start every line with the given text.
+    Text(String),
+
+    /// Start the first line of each row with the result of a function of
+    /// the row’s index, starting from 0, padded on the left to the given
+    /// width. Lines that aren’t the first of a row, such as the rest of a
+    /// wrapped row or a group header, start with that many spaces. Results
+    /// wider than the width push the rest of their line along.
+    Custom(Width, Callback<dyn Fn(usize) -> String + Send + Sync>),
+}
+
+impl LinePrefix {
+
+    /// Starts each row with the result of the given function, padded to
+    /// the given width.
+    pub fn custom(width: Width, prefix: impl Fn(usize) -> String + Send + Sync + 'static) -> Self {
+        LinePrefix::Custom(width, Callback(Arc::new(prefix)))
+    }
+
+    /// The width the prefix takes up, measuring emoji according to the
+    /// given policy.
+    fn width(&self, policy: EmojiWidth) -> Width {
+        match self {
+            LinePrefix::Text(text)        => policy.width(text),
+            LinePrefix::Custom(width, _)  => *width,
+        }
+    }
+
+    /// The prefix for the first line of the row with the given index, or
+    /// for any other line if there isn’t one, measuring emoji according to
+    /// the given policy.
+    fn for_row(&self, row: Option<usize>, policy: EmojiWidth) -> String {
+        match (self, row) {
+            (LinePrefix::Text(text), _)                     => text.clone(),
+            (LinePrefix::Custom(width, prefix), Some(row))  => {
+                let prefix = (prefix.0)(row);
+                let padding = width.saturating_sub(policy.width(&prefix));
+                pad_string(&prefix, padding, Alignment::Right, ' ')
+            },
+            (LinePrefix::Custom(width, _), None)            => spaces(*width),
+        }
+    }
+}
+
+/// A column of labels shown to the left of the grid, one for each row,
+/// such as the numbers to type to pick an item from a list.
+#[derive(PartialEq, Debug, Clone)]
+#[non_exhaustive]
+pub enum IndexColumn {
+
+    /// Number the rows, starting from 1.
+    OneBased,
+
+    /// Label the rows using a function of their index, starting from 0.
+    Custom(Callback<dyn Fn(usize) -> String + Send + Sync>),
+}
+
+impl IndexColumn {
+
+    /// Labels the rows using the given function of their index.
+    pub fn custom(label: impl Fn(usize) -> String + Send + Sync + 'static) -> Self {
+        IndexColumn::Custom(Callback(Arc::new(label)))
+    }
+
+    /// The label for the row with the given index.
+    fn label(&self, row: usize) -> String {
+        match self {
+            IndexColumn::OneBased       => (row + 1).to_string(),
+            IndexColumn::Custom(label)  => (label.0)(row),
+        }
+    }
+
+    /// The width of the widest label in a grid with the given number of rows.
+    fn width(&self, num_lines: usize) -> Width {
+        match self {
+            IndexColumn::OneBased  => if num_lines == 0 { 0 } else { num_lines.to_string().len() },
+            IndexColumn::Custom(_) => {
+                (0 .. num_lines).map(|row| UnicodeWidthStr::width(&self.label(row)[..]))
+                                .max().unwrap_or(0)
+            },
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
 pub struct GridOptions {
     filling: Filling,
+    separators: BTreeMap<usize, Filling>,
+    column_groups: Option<(usize, Filling)>,
     direction: Direction,
+    placeholder: Option<Cell>,
+    max_column_width: Option<Width>,
+    max_cell_width: Option<Width>,
+    column_granularity: Option<Width>,
+    overflow: Overflow,
+    layout_score: LayoutScore,
+    tie_break: TieBreak,
+    horizontal_alignment: GridAlign,
+    pad_lines: bool,
+    continuous_rules: bool,
+    output_mode: OutputMode,
+    padding_placement: PaddingPlacement,
+    index_column: Option<IndexColumn>,
+    truncate_side: TruncateSide,
+    emoji_width: EmojiWidth,
+    column_headers: Option<Callback<dyn Fn(usize) -> Cell + Send + Sync>>,
+    auto_align_numbers: bool,
+    min_separator_width: Option<Width>,
+    gutter: Option<Gutter>,
+    column_weights: Vec<usize>,
+    justify: bool,
+    line_prefix: Option<LinePrefix>,
+    annotations: Vec<Cell>,
+    max_line_bytes: Option<usize>,
 }
 
-#[derive(PartialEq, Debug)]
-pub struct Grid {
-    options: GridOptions,
-    cells: Vec<Cell>,
-    widest_cell_length: Width,
-    width_sum: Width,
-    cell_count: usize,
-}
-
-impl Grid {
-    pub fn new(options: GridOptions) -> Self {
-        let cells = Vec::new();
+impl GridOptions {
+    pub fn new(filling: Filling, direction: Direction) -> Self {
         Self {
-            options,
-            cells,
-            widest_cell_length: 0,
-            width_sum: 0,
-            cell_count: 0,
+            filling,
+            separators: BTreeMap::new(),
+            column_groups: None,
+            direction,
+            placeholder: None,
+            max_column_width: None,
+            max_cell_width: None,
+            column_granularity: None,
+            overflow: Overflow::Truncate,
+            layout_score: LayoutScore::FewestLines,
+            tie_break: TieBreak::FirstFit,
+            horizontal_alignment: GridAlign::Left,
+            pad_lines: false,
+            continuous_rules: false,
+            output_mode: OutputMode::Grid,
+            padding_placement: PaddingPlacement::BeforeSeparator,
+            index_column: None,
+            truncate_side: TruncateSide::Right,
+            emoji_width: EmojiWidth::Measured,
+            column_headers: None,
+            auto_align_numbers: false,
+            min_separator_width: None,
+            gutter: None,
+            column_weights: Vec::new(),
+            justify: false,
+            line_prefix: None,
+            annotations: Vec::new(),
+            max_line_bytes: None,
+        }
+    }
+
+    /// Stops any column from growing wider than the given width. Cells that
+    /// are wider get truncated or wrapped, depending on the overflow policy,
+    /// while narrower cells in the same column are laid out as normal.
+    pub fn max_column_width(mut self, width: Width) -> Self {
+        self.max_column_width = Some(width);
+        self
+    }
+
+    /// Sets the widest a cell can be and still get added to the grid with
+    /// `Grid::try_add`, so absurdly wide input can be turned away, or cut
+    /// down, as it arrives. Cells added with `Grid::add` aren’t checked.
+    pub fn max_cell_width(mut self, width: Width) -> Self {
+        self.max_cell_width = Some(width);
+        self
+    }
+
+    /// Rounds the width of every column up to a multiple of the given
+    /// width, such as 8 to line the columns up with tab stops. The rounding
+    /// is done before the grid is fitted into a width, so it’s taken into
+    /// account when working out how many columns fit. Columns can end up
+    /// wider than the maximum column width, if that isn’t a multiple.
+    pub fn column_granularity(mut self, granularity: Width) -> Self {
+        self.column_granularity = Some(granularity);
+        self
+    }
+
+    /// Sets what happens to cells wider than the maximum column width.
+    /// Cells are truncated by default.
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sets how to choose between the layouts that fit into the width. By
+    /// default, the layout with the fewest lines is chosen.
+    pub fn layout_score(mut self, layout_score: LayoutScore) -> Self {
+        self.layout_score = layout_score;
+        self
+    }
+
+    /// Sets how to choose between layouts with the same score. Layouts never
+    /// tie when the score is the number of lines, so this only matters for
+    /// the other layout scores.
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Sets where the grid goes within the width it was fitted into, such as
+    /// centering it on a status screen. Grids displayed with a layout from
+    /// `Display::from_layout` have no such width, so they stay on the left.
+    pub fn horizontal_alignment(mut self, alignment: GridAlign) -> Self {
+        self.horizontal_alignment = alignment;
+        self
+    }
+
+    /// Shows a header above each column, made by calling the given function
+    /// with the column’s index, such as `|x| format!("col {}", x + 1)`. The
+    /// number of columns isn’t known until the grid has been fitted into a
+    /// width, so the headers are made afresh for each layout that gets
+    /// tried, and columns are widened to fit them. Like an index column,
+    /// this means trying every possible number of lines.
+    pub fn column_headers<C: Into<Cell>>(mut self, header: impl Fn(usize) -> C + Send + Sync + 'static) -> Self {
+        self.column_headers = Some(Callback(Arc::new(move |x| header(x).into())));
+        self
+    }
+
+    /// Right-aligns cells that look like numbers as they get added to the
+    /// grid, such as `42`, `-1,234.5`, `87%`, or sizes like `4.2K` and
+    /// `4.2 KiB`, so columns of numbers line up.
+    pub fn auto_align_numbers(mut self, auto_align_numbers: bool) -> Self {
+        self.auto_align_numbers = auto_align_numbers;
+        self
+    }
+
+    /// Sets which part of a cell’s contents gets cut out when it’s
+    /// truncated. Cells lose their ends by default.
+    pub fn truncate_side(mut self, side: TruncateSide) -> Self {
+        self.truncate_side = side;
+        self
+    }
+
+    /// Sets how wide emoji sequences are taken to be. Unless they’re
+    /// measured from the Unicode tables, which is the default, cells get
+    /// measured again as they’re added to the grid, and the same widths are
+    /// used when they’re truncated, wrapped, and padded. Group and column
+    /// headers, the placeholder, annotations and the line prefix are
+    /// measured the same way.
+    pub fn emoji_width(mut self, emoji_width: EmojiWidth) -> Self {
+        self.emoji_width = emoji_width;
+        self.placeholder = self.placeholder.take().map(|cell| self.measured(cell));
+        self.annotations = std::mem::take(&mut self.annotations).into_iter().map(|annotation| self.measured(annotation)).collect();
+        self
+    }
+
+    /// Lets the separators between columns shrink, down to the given width,
+    /// when that fits the grid into fewer lines, so a grid that’s only a
+    /// few characters too wide for another column gets narrower gaps rather
+    /// than an extra line. The separators shrink as little as they can. This
+    /// only applies to `Filling::Spaces` fillings, and not to separators set
+    /// with `separator_at`.
+    ///
+    /// A layout kept from a display with shrunken separators doesn’t record
+    /// them, so `Display::from_layout` shows it with the usual separators.
+    pub fn min_separator_width(mut self, width: Width) -> Self {
+        self.min_separator_width = Some(width);
+        self
+    }
+
+    /// Shows a right-aligned column of row labels to the left of the grid,
+    /// separated from it by the grid’s filling. The labels are taken into
+    /// account when fitting the grid into a width, which means trying every
+    /// possible number of lines, as with `candidate_layouts`.
+    pub fn index_column(mut self, index_column: IndexColumn) -> Self {
+        self.index_column = Some(index_column);
+        self
+    }
+
+    /// Pads every rendered line with trailing spaces so it’s exactly as wide
+    /// as `Display::width`, plus any indentation from the grid’s horizontal
+    /// alignment. Lines are left unpadded by default, so they can end early
+    /// when the last column is left-aligned, a cell has been wrapped to
+    /// fewer characters than its column, or the grid ends part-way through
+    /// a row. Padding them is useful when drawing a border around the grid.
+    pub fn pad_lines(mut self, pad_lines: bool) -> Self {
+        self.pad_lines = pad_lines;
+        self
+    }
+
+    /// Draws text separators on every line of a row, rather than just the
+    /// first, so they join up into unbroken rules down the grid when cells
+    /// get wrapped over several lines. Lines after the first then carry on
+    /// to the last cell in the row, even if it has nothing left to show.
+    pub fn continuous_rules(mut self, continuous_rules: bool) -> Self {
+        self.continuous_rules = continuous_rules;
+        self
+    }
+
+    /// Sets how the grid gets written out when it’s displayed, so the same
+    /// grid can be shown plainly when the user has asked for that.
+    pub fn output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// Sets which side of the separator after each cell its padding goes
+    /// on. By default, cells are padded before the separator.
+    pub fn padding_placement(mut self, placement: PaddingPlacement) -> Self {
+        self.padding_placement = placement;
+        self
+    }
+
+    /// Uses a different separator between the column at the given index and
+    /// the one after it, such as a wider gap between two groups of columns.
+    /// Every other pair of columns keeps the grid’s usual filling.
+    pub fn separator_at(mut self, index: usize, filling: Filling) -> Self {
+        self.separators.insert(index, filling);
+        self
+    }
+
+    /// Uses a different separator after every `n` columns, such as `┃`
+    /// where the rest of the grid uses `│`, to split a dense grid into
+    /// groups of related columns. Separators set for a particular column
+    /// with `separator_at` take precedence, and an `n` of 0 leaves every
+    /// separator as it is.
+    pub fn group_columns(mut self, n: usize, separator: Filling) -> Self {
+        self.column_groups = Some((n, separator));
+        self
+    }
+
+    /// Renders the given cell in grid positions that have no cell of their
+    /// own, so every row has the same number of columns. The placeholder’s
+    /// width is taken into account when sizing the columns it appears in.
+    pub fn placeholder(mut self, cell: impl Into<Cell>) -> Self {
+        self.placeholder = Some(self.measured(cell.into()));
+        self
+    }
+
+    /// Measures a cell again if the emoji width policy measures emoji
+    /// differently from `Cell::from`.
+    fn measured(&self, mut cell: Cell) -> Cell {
+        if self.emoji_width != EmojiWidth::Measured {
+            cell.width = self.emoji_width.width(&cell.contents);
+        }
+
+        cell
+    }
+
+    /// Aligns a cell to the right if it looks like a number and the options
+    /// ask for numbers to be aligned that way.
+    fn aligned(&self, mut cell: Cell) -> Cell {
+        if self.auto_align_numbers && looks_like_number(&cell.contents) {
+            cell.alignment = Alignment::Right;
+        }
+
+        cell
+    }
+
+    /// Makes each column at least as wide as its column header, if there
+    /// are any.
+    fn widen_for_headers(&self, widths: &mut [Width]) {
+        if self.column_headers.is_some() {
+            for (x, width) in widths.iter_mut().enumerate() {
+                *width = max(*width, self.cell_width(&self.column_header(x)));
+            }
+        }
+    }
+
+    /// The header for the column with the given index, measured according
+    /// to the emoji width policy. There has to be a column header function.
+    fn column_header(&self, x: usize) -> Cell {
+        let column_headers = self.column_headers.as_ref().expect("column headers");
+        self.measured((column_headers.0)(x))
+    }
+
+    /// The width a cell takes up for the purposes of laying out the grid,
+    /// taking the maximum column width and the column granularity into
+    /// account. Rounding every cell up rounds every column up with it, as a
+    /// column is as wide as its widest cell. A cell that gets wrapped is
+    /// never narrower than its widest character, as wrapping can’t split
+    /// one.
+    fn cell_width(&self, cell: &Cell) -> Width {
+        self.width_in_layout(&cell.contents, cell.width)
+    }
+
+    /// The width contents that measure the given width take up in the
+    /// layout, as with `cell_width`.
+    fn width_in_layout(&self, contents: &str, width: Width) -> Width {
+        let width = match self.max_column_width {
+            Some(maximum) if width > maximum && self.overflow == Overflow::Wrap => {
+                max(maximum, widest_cluster(contents, self.emoji_width))
+            },
+            Some(maximum) => min(width, maximum),
+            None          => width,
+        };
+
+        match self.column_granularity {
+            Some(granularity) if granularity > 1  => width.next_multiple_of(granularity),
+            _                                     => width,
+        }
+    }
+
+    /// Returns the filling put between columns, set with `GridOptions::new`.
+    pub fn get_filling(&self) -> &Filling {
+        &self.filling
+    }
+
+    /// Returns the separator set for after the column at the given index
+    /// with `separator_at`, if there is one.
+    pub fn get_separator_at(&self, index: usize) -> Option<&Filling> {
+        self.separators.get(&index)
+    }
+
+    /// Returns the size of each group of columns and the separator after it.
+    pub fn get_group_columns(&self) -> Option<(usize, &Filling)> {
+        self.column_groups.as_ref().map(|(n, filling)| (*n, filling))
+    }
+
+    /// Returns the direction the cells flow in, set with `GridOptions::new`.
+    pub fn get_direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the cell shown in positions that have no cell of their own.
+    pub fn get_placeholder(&self) -> Option<&Cell> {
+        self.placeholder.as_ref()
+    }
+
+    /// Returns the width columns are capped at, if there is one.
+    pub fn get_max_column_width(&self) -> Option<Width> {
+        self.max_column_width
+    }
+
+    /// Returns the width above which `Grid::try_add` turns cells away.
+    pub fn get_max_cell_width(&self) -> Option<Width> {
+        self.max_cell_width
+    }
+
+    /// Returns the multiple column widths get rounded up to, if there is one.
+    pub fn get_column_granularity(&self) -> Option<Width> {
+        self.column_granularity
+    }
+
+    /// Returns what happens to cells wider than the maximum column width.
+    pub fn get_overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Returns how candidate layouts are scored against each other.
+    pub fn get_layout_score(&self) -> &LayoutScore {
+        &self.layout_score
+    }
+
+    /// Returns how layouts with the same score are picked between.
+    pub fn get_tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
+
+    /// Returns where the grid goes within the width it gets fitted into.
+    pub fn get_horizontal_alignment(&self) -> GridAlign {
+        self.horizontal_alignment
+    }
+
+    /// Returns whether every line gets padded out to the grid’s full width.
+    pub fn get_pad_lines(&self) -> bool {
+        self.pad_lines
+    }
+
+    /// Returns whether text separators carry on down wrapped rows.
+    pub fn get_continuous_rules(&self) -> bool {
+        self.continuous_rules
+    }
+
+    /// Returns whether the grid is rendered as a grid or as plain lines.
+    pub fn get_output_mode(&self) -> OutputMode {
+        self.output_mode
+    }
+
+    /// Returns which side of the separator the padding of a cell goes on.
+    pub fn get_padding_placement(&self) -> PaddingPlacement {
+        self.padding_placement
+    }
+
+    /// Returns the column of numbers at the start of each line, if any.
+    pub fn get_index_column(&self) -> Option<&IndexColumn> {
+        self.index_column.as_ref()
+    }
+
+    /// Returns which part of a cell gets cut out when it’s truncated.
+    pub fn get_truncate_side(&self) -> TruncateSide {
+        self.truncate_side
+    }
+
+    /// Returns how wide emoji sequences are taken to be.
+    pub fn get_emoji_width(&self) -> EmojiWidth {
+        self.emoji_width
+    }
+
+    /// Returns the header for the column at the given index, if the options
+    /// have column headers.
+    pub fn get_column_header(&self, index: usize) -> Option<Cell> {
+        self.column_headers.as_ref().map(|header| (header.0)(index))
+    }
+
+    /// Returns whether cells that look like numbers get aligned to the right.
+    pub fn get_auto_align_numbers(&self) -> bool {
+        self.auto_align_numbers
+    }
+
+    /// Returns the width the separators can shrink down to, if they can.
+    pub fn get_min_separator_width(&self) -> Option<Width> {
+        self.min_separator_width
+    }
+
+    /// Returns the gutter that sets the width of the separators, if any.
+    pub fn get_gutter(&self) -> Option<&Gutter> {
+        self.gutter.as_ref()
+    }
+
+    /// Returns the weights the room left over is shared out by.
+    pub fn get_column_weights(&self) -> &[usize] {
+        &self.column_weights
+    }
+
+    /// Returns whether the gaps between columns get widened to fill the width.
+    pub fn get_justify(&self) -> bool {
+        self.justify
+    }
+
+    /// Returns what each line starts with, if anything.
+    pub fn get_line_prefix(&self) -> Option<&LinePrefix> {
+        self.line_prefix.as_ref()
+    }
+
+    /// Returns the annotations that go at the end of each row.
+    pub fn get_annotations(&self) -> &[Cell] {
+        &self.annotations
+    }
+
+    /// Returns the most bytes a rendered line can take up, if there’s a limit.
+    pub fn get_max_line_bytes(&self) -> Option<usize> {
+        self.max_line_bytes
+    }
+
+    /// Checks the options for combinations that make for unreadable grids,
+    /// such as separators with no width, which leave nothing between the
+    /// contents of neighbouring columns. `Grid::new` accepts these as they
+    /// are, and `Grid::try_new` rejects them.
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        let separators = Some((None, &self.filling)).into_iter()
+            .chain(self.column_groups.iter().filter(|(n, _)| *n > 0).map(|(n, filling)| (Some(n - 1), filling)))
+            .chain(self.separators.iter().map(|(&index, filling)| (Some(index), filling)));
+
+        for (index, filling) in separators {
+            if filling.width() == 0 {
+                return Err(OptionsError::ZeroWidthSeparator { index });
+            }
+            else if let Filling::Text(text) = filling {
+                if text.contains(['\n', '\r']) {
+                    return Err(OptionsError::MultiLineSeparator { index });
+                }
+            }
+        }
+
+        if self.max_column_width == Some(0) {
+            return Err(OptionsError::ZeroMaxColumnWidth);
+        }
+
+        Ok(())
+    }
+
+    /// Varies the number of spaces between columns with the number of
+    /// columns, in place of the width of the grid’s filling. The grid’s
+    /// minimum separator width doesn’t apply to grids with a gutter.
+    pub fn gutter(mut self, gutter: Gutter) -> Self {
+        self.gutter = Some(gutter);
+        self
+    }
+
+    /// Starts each line of the rendered grid with the given prefix, fitting
+    /// the grid into the width that’s left. Plain output doesn’t get one.
+    pub fn line_prefix(mut self, line_prefix: LinePrefix) -> Self {
+        self.line_prefix = Some(line_prefix);
+        self
+    }
+
+    /// Ends rows with annotations, such as the dates in `ls -l` or comments
+    /// after lines of code, flush with the right-hand edge of the width the
+    /// grid was fitted into. The first annotation goes on the first row, and
+    /// so on, with empty annotations leaving their rows without one. The
+    /// grid is fitted into the width left over by the widest annotation and
+    /// the filling before it, so this is most useful for grids whose cells
+    /// already come arranged into rows, where it’s known which row each
+    /// annotation goes on. Grids displayed without being fitted into a width
+    /// put their annotations right after the last column.
+    pub fn annotations<C: Into<Cell>>(mut self, annotations: impl IntoIterator<Item = C>) -> Self {
+        self.annotations = annotations.into_iter().map(|annotation| self.measured(annotation.into())).collect();
+        self
+    }
+
+    /// Stops any rendered line from taking up more than the given number of
+    /// bytes, for protocols that limit the length of a message, as well as
+    /// fitting the grid into a width. Lines that are too long get cut at
+    /// the last character that fits, leaving room for an ellipsis, so the
+    /// cells at the end of them are cut short or left out. Line breaks
+    /// aren’t counted, but the strings around styled cells are.
+    pub fn max_line_bytes(mut self, max_line_bytes: usize) -> Self {
+        self.max_line_bytes = Some(max_line_bytes);
+        self
+    }
+
+    /// The width of the prefix at the start of each line.
+    fn prefix_width(&self) -> Width {
+        self.line_prefix.as_ref().map_or(0, |line_prefix| line_prefix.width(self.emoji_width))
+    }
+
+    /// The width taken up by the annotations at the end of rows, including
+    /// the filling before them, or 0 if there aren’t any.
+    fn annotation_width(&self) -> Width {
+        match self.annotations.iter().map(|annotation| annotation.width).max() {
+            Some(widest) if widest > 0  => widest + self.filling.width(),
+            _                           => 0,
+        }
+    }
+
+    /// The width on each line set aside for things other than the columns:
+    /// the line prefix and the annotations.
+    fn reserved_width(&self) -> Width {
+        self.prefix_width() + self.annotation_width()
+    }
+
+    /// The prefix for the first line of the row with the given index, or
+    /// for any other line if there isn’t one.
+    fn prefix(&self, row: Option<usize>) -> String {
+        self.line_prefix.as_ref().map_or_else(String::new, |line_prefix| line_prefix.for_row(row, self.emoji_width))
+    }
+
+    /// Widens the columns to take up the whole width the grid gets fitted
+    /// into, sharing out the room left over once every cell fits between
+    /// the columns in proportion to their weights, from left to right.
+    /// Columns past the end of the weights get none of it, so `[0, 1]`
+    /// gives all of it to the second column. Grids are left as narrow as
+    /// they can be by default, or if none of their columns have weight.
+    pub fn column_weights(mut self, weights: impl Into<Vec<usize>>) -> Self {
+        self.column_weights = weights.into();
+        self
+    }
+
+    /// Widens the gaps between columns so the grid spans the whole width it
+    /// gets fitted into, like justified text, with the room shared out as
+    /// evenly as it can be. Text separators stay in the middle of their
+    /// gaps. Column weights get the room first, so there’s none left to
+    /// justify the grid with if any column has weight.
+    pub fn justify(mut self, justify: bool) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Shares out the room the layout leaves in the width between its
+    /// columns, according to their weights.
+    fn spread_room(&self, layout: &mut Layout, maximum_width: Width) {
+        let num_columns = layout.widths.len();
+        let weights = &self.column_weights[.. min(num_columns, self.column_weights.len())];
+        let total_weight: usize = weights.iter().sum();
+        let room = maximum_width.saturating_sub(self.layout_width(layout));
+        if total_weight == 0 || room == 0 {
+            return;
+        }
+
+        let mut left_over = room;
+        for (width, &weight) in layout.widths.iter_mut().zip(weights) {
+            let share = room * weight / total_weight;
+            *width += share;
+            left_over -= share;
+        }
+
+        // Rounding down leaves a little room, which goes to the weighted
+        // columns one at a time.
+        for (width, _) in layout.widths.iter_mut().zip(weights).filter(|&(_, &weight)| weight > 0).take(left_over) {
+            *width += 1;
+        }
+    }
+
+    /// The separator that goes after the column at the given index, in a
+    /// grid with the given number of columns.
+    fn separator(&self, index: usize, num_columns: usize) -> Cow<'_, Filling> {
+        self.separators(None).separator(index, num_columns)
+    }
+
+    /// The combined width of the separators between the given number of
+    /// columns.
+    fn separators_width(&self, num_columns: usize) -> Width {
+        self.separators(None).separators_width(num_columns)
+    }
+
+    /// The width of a grid laid out with the given layout, separators
+    /// included.
+    fn layout_width(&self, layout: &Layout) -> Width {
+        self.separators(None).layout_width(layout)
+    }
+
+    /// The width of the index column for a grid with the given number of
+    /// lines, including the separator after it, or 0 if there isn’t one.
+    fn index_width(&self, num_lines: usize) -> Width {
+        self.separators(None).index_width(num_lines)
+    }
+
+    /// The separators, with the spaces between columns narrowed to the
+    /// given width if there is one.
+    fn separators(&self, spacing: Option<Width>) -> Separators<'_> {
+        Separators { options: self, spacing }
+    }
+}
+
+/// The separators a grid’s options put between its columns, with the spaces
+/// between them narrowed to a different width if there is one, so narrower
+/// separators can be tried without copying the options.
+#[derive(Copy, Clone)]
+struct Separators<'a> {
+    options: &'a GridOptions,
+
+    /// The width of the spaces between columns, if it isn’t the width of
+    /// the options’ filling.
+    spacing: Option<Width>,
+}
+
+impl<'a> Separators<'a> {
+
+    /// The separator that goes after the column at the given index, in a
+    /// grid with the given number of columns.
+    fn separator(&self, index: usize, num_columns: usize) -> Cow<'a, Filling> {
+        match (self.options.separators.get(&index), &self.options.column_groups) {
+            (Some(filling), _)                                           => Cow::Borrowed(filling),
+            (None, Some((n, filling))) if (index + 1).is_multiple_of(*n)  => Cow::Borrowed(filling),
+            (None, _)                                                    => self.column_filling(num_columns),
+        }
+    }
+
+    /// The filling between columns in a grid with the given number of
+    /// columns, which depends on the gutter if there is one.
+    fn column_filling(&self, num_columns: usize) -> Cow<'a, Filling> {
+        match (&self.options.filling, &self.options.gutter, self.spacing) {
+            (Filling::Spaces(_), Some(gutter), _)         => Cow::Owned(Filling::Spaces(gutter.width(num_columns))),
+            (Filling::Spaces(_), None, Some(spacing))     => Cow::Owned(Filling::Spaces(spacing)),
+            (filling, _, _)                               => Cow::Borrowed(filling),
+        }
+    }
+
+    /// The combined width of the separators between the given number of
+    /// columns.
+    fn separators_width(&self, num_columns: usize) -> Width {
+        if self.options.separators.is_empty() && self.options.column_groups.is_none() {
+            return num_columns.saturating_sub(1) * self.column_filling(num_columns).width();
+        }
+
+        (0 .. num_columns.saturating_sub(1)).map(|index| self.separator(index, num_columns).width()).sum()
+    }
+
+    /// The width of a grid laid out with the given layout, separators
+    /// included.
+    fn layout_width(&self, layout: &Layout) -> Width {
+        layout.widths.iter().sum::<Width>() + self.separators_width(layout.widths.len())
+            + self.index_width(layout.num_lines) + self.options.reserved_width()
+    }
+
+    /// The width of the index column for a grid with the given number of
+    /// lines, including the separator after it, or 0 if there isn’t one.
+    fn index_width(&self, num_lines: usize) -> Width {
+        match &self.options.index_column {
+            Some(index_column) if num_lines > 0  => index_column.width(num_lines) + self.spacing.unwrap_or(self.options.filling.width()),
+            _                                    => 0,
+        }
+    }
+}
+
+/// A problem with a grid’s options, found by `GridOptions::validate`.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum OptionsError {
+
+    /// A separator has no width, such as `Filling::Spaces(0)` or an empty
+    /// `Filling::Text`, so the columns on either side of it run together.
+    /// The index is that of the column before the separator, if it was set
+    /// with `GridOptions::separator_at`.
+    ZeroWidthSeparator { index: Option<usize> },
+
+    /// A text separator contains a line break, which would break every row
+    /// of the grid across several lines.
+    MultiLineSeparator { index: Option<usize> },
+
+    /// The maximum column width is zero, leaving no room for any cell.
+    ZeroMaxColumnWidth,
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroWidthSeparator { index: None }         => write!(f, "the separator between columns has no width"),
+            Self::ZeroWidthSeparator { index: Some(index) }  => write!(f, "the separator after column {} has no width", index),
+            Self::MultiLineSeparator { index: None }         => write!(f, "the separator between columns contains a line break"),
+            Self::MultiLineSeparator { index: Some(index) }  => write!(f, "the separator after column {} contains a line break", index),
+            Self::ZeroMaxColumnWidth                         => write!(f, "the maximum column width is zero"),
+        }
+    }
+}
+
+impl std::error::Error for OptionsError {}
+
+/// A cell wider than the options’ maximum cell width, turned away by
+/// `Grid::try_add`. The cell is handed back, so it can be cut down and
+/// added again.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CellTooWide {
+
+    /// The cell that was turned away, measured as the grid measures it.
+    pub cell: Cell,
+
+    /// The widest a cell can be.
+    pub maximum: Width,
+}
+
+impl fmt::Display for CellTooWide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the cell is {} columns wide, more than the maximum of {}", self.cell.width, self.maximum)
+    }
+}
+
+impl std::error::Error for CellTooWide {}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Grid {
+    options: GridOptions,
+    cells: Vec<Cell>,
+    widest_cell_length: Width,
+
+    /// The index of the first of the widest cells, if there are any cells.
+    widest_cell_index: Option<usize>,
+
+    width_sum: Width,
+    cell_count: usize,
+
+    /// How many cells take up each width in the layout, kept up to date as
+    /// cells are added so fitting doesn’t have to sort them.
+    width_counts: BTreeMap<Width, usize>,
+
+    /// The width and number of lines of the last layout found by
+    /// `current_fit`, which is where the next search starts from.
+    fit_hint: Option<(Width, usize)>,
+
+    /// The number of columns of the last layout found by `sticky_fit`,
+    /// which it keeps to if it can.
+    sticky_columns: Option<usize>,
+
+    /// The number of columns, for grids whose cells already come arranged
+    /// into rows.
+    fixed_columns: Option<usize>,
+
+    /// The index of the first cell in each group, along with the header
+    /// shown above it. Indices count hidden cells as well as shown ones.
+    groups: Vec<(usize, Cell)>,
+
+    /// The cells that have been hidden, taken out of `cells`, by the index
+    /// they’d have if every cell were shown.
+    hidden: BTreeMap<usize, Cell>,
+}
+
+impl Grid {
+
+    /// Creates an empty grid with the given options. The options are used
+    /// as they are, so a separator with no width lets neighbouring columns
+    /// run together, with a left-aligned cell followed directly by a
+    /// right-aligned one. Use `Grid::try_new` to have them checked first.
+    pub fn new(options: GridOptions) -> Self {
+        let cells = Vec::new();
+        Self {
+            options,
+            cells,
+            widest_cell_length: 0,
+            widest_cell_index: None,
+            width_sum: 0,
+            cell_count: 0,
+            width_counts: BTreeMap::new(),
+            fit_hint: None,
+            sticky_columns: None,
+            fixed_columns: None,
+            groups: Vec::new(),
+            hidden: BTreeMap::new(),
+        }
+    }
+
+    /// Creates an empty grid with the given options, after checking them
+    /// with `GridOptions::validate`.
+    pub fn try_new(options: GridOptions) -> Result<Self, OptionsError> {
+        options.validate()?;
+        Ok(Self::new(options))
+    }
+
+    /// Creates a grid from cells that are already arranged into rows. The
+    /// grid keeps them that way, so fitting it into a width only has to work
+    /// out how wide each column should be, rather than how many columns to
+    /// use. Rows with fewer cells than the longest row are padded with empty
+    /// cells.
+    ///
+    /// The cells are laid out from left to right, whatever direction the
+    /// options ask for.
+    pub fn from_rows<C: Into<Cell>>(mut options: GridOptions, rows: Vec<Vec<C>>) -> Self {
+        options.direction = Direction::LeftToRight;
+
+        let num_columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut grid = Self::new(options);
+        grid.reserve(rows.len() * num_columns);
+
+        for row in rows {
+            let padding = num_columns - row.len();
+            for cell in row {
+                grid.add(cell.into());
+            }
+            for _ in 0 .. padding {
+                grid.add(Cell::from(""));
+            }
+        }
+
+        if num_columns > 0 {
+            grid.fixed_columns = Some(num_columns);
+        }
+
+        grid
+    }
+
+    /// Creates a grid from lines of text the way `column -t` does, splitting
+    /// each line into fields that become the cells of a row, as with
+    /// `Grid::from_rows`. Lines are split on runs of whitespace, unless
+    /// delimiters are given, in which case they’re split on every one of
+    /// the delimiter characters and empty fields are kept. Empty lines are
+    /// skipped.
+    ///
+    /// `column -t` separates its columns with two spaces, so options with
+    /// `Filling::Spaces(2)` give the same output when the grid is fitted
+    /// into a width it fits in.
+    pub fn from_lines<'a>(options: GridOptions, lines: impl IntoIterator<Item = &'a str>, delimiters: Option<&str>) -> Self {
+        let rows = lines.into_iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match delimiters {
+                Some(delimiters)  => line.split(|c| delimiters.contains(c)).map(Cell::from).collect(),
+                None              => line.split_whitespace().map(Cell::from).collect(),
+            })
+            .collect();
+
+        Self::from_rows(options, rows)
+    }
+
+    /// Adds a column of cells to the right of the grid, for data that comes
+    /// arranged into columns rather than rows, such as a column of names
+    /// followed by a column of sizes. The grid keeps its columns as they
+    /// are given, as with `Grid::from_rows`, and each row is made up of the
+    /// cells at the same index in each column. Columns shorter than the
+    /// others are padded with empty cells at the bottom.
+    ///
+    /// Cells added to the grid some other way are taken to make up its
+    /// first column, unless they were arranged into rows already. The cells
+    /// already in the grid keep their groups, and stay hidden if they were,
+    /// but their indices move along to make room for the new column.
+    pub fn add_column<C: Into<Cell>>(&mut self, column: Vec<C>) {
+        let num_cells = self.cells.len() + self.hidden.len();
+        let num_columns = match self.fixed_columns {
+            Some(num_columns)       => num_columns,
+            None if num_cells == 0  => 0,
+            None                    => 1,
+        };
+
+        let num_rows = match num_columns {
+            0  => column.len(),
+            _  => max(num_cells.div_ceil(num_columns), column.len()),
+        };
+        let new_index = |index: usize| index + index.checked_div(num_columns).unwrap_or(0);
+
+        let column: Vec<Cell> = column.into_iter().map(|cell| self.options.aligned(self.options.measured(cell.into()))).collect();
+        let mut column = column.into_iter();
+        let mut shown = std::mem::take(&mut self.cells).into_iter();
+        let mut hidden = std::mem::take(&mut self.hidden);
+        let mut cells = Vec::with_capacity(num_rows * (num_columns + 1) - hidden.len());
+
+        for row in 0 .. num_rows {
+            for index in row * num_columns .. (row + 1) * num_columns {
+                match hidden.remove(&index) {
+                    Some(cell)  => { self.hidden.insert(new_index(index), cell); },
+                    None        => cells.push(shown.next().unwrap_or_else(|| Cell::from(""))),
+                }
+            }
+
+            cells.push(column.next().unwrap_or_else(|| Cell::from("")));
+        }
+
+        for (start, _) in &mut self.groups {
+            *start = new_index(*start);
+        }
+
+        self.cells = cells;
+        self.options.direction = Direction::LeftToRight;
+        self.fixed_columns = Some(num_columns + 1);
+        self.recount();
+    }
+
+    /// Builds a grid out of arbitrary bytes, for fuzzing. The first four
+    /// bytes pick the options, and the rest are split on zero bytes into
+    /// cells, with any cell starting with `#` starting a group instead.
+    /// Every input gives some grid, so a fuzzer can go looking for
+    /// combinations of options and cells that make fitting or rendering
+    /// misbehave.
+    #[cfg(feature = "fuzz")]
+    pub fn from_fuzz_input(data: &[u8]) -> Self {
+        let byte = |index: usize| data.get(index).copied().unwrap_or(0);
+        let (flags, separator_width, max_column_width, extra) = (byte(0), byte(1) % 8, byte(2), byte(3));
+        let flag = |bit: u8| flags & (1 << bit) != 0;
+
+        let direction = if flag(0) { Direction::TopToBottom } else { Direction::LeftToRight };
+        let filling = if flag(1) { Filling::Text("|".repeat(separator_width.into())) }
+                      else       { Filling::Spaces(separator_width.into()) };
+
+        let mut options = GridOptions::new(filling, direction)
+            .overflow(if flag(2) { Overflow::Wrap } else { Overflow::Truncate })
+            .pad_lines(flag(4))
+            .continuous_rules(flag(7))
+            .layout_score(match extra % 4 {
+                0 => LayoutScore::FewestLines,
+                1 => LayoutScore::NarrowestWidth,
+                2 => LayoutScore::Densest,
+                _ => LayoutScore::BalancedColumns,
+            })
+            .tie_break(if extra & 4 != 0 { TieBreak::WidestColumnLast } else { TieBreak::FirstFit })
+            .horizontal_alignment(if extra & 8 != 0 { GridAlign::Center } else { GridAlign::Left })
+            .auto_align_numbers(extra & 16 != 0);
+
+        if flag(3) {
+            options = options.max_column_width(max_column_width.into());
+        }
+        if flag(5) {
+            options = options.index_column(IndexColumn::OneBased);
+        }
+        if flag(6) {
+            options = options.placeholder("-");
+        }
+        if extra & 32 != 0 {
+            options = options.min_separator_width(1);
+        }
+
+        let mut grid = Grid::new(options);
+        for contents in data.get(4 ..).unwrap_or_default().split(|&b| b == 0) {
+            let contents = String::from_utf8_lossy(contents);
+            match contents.strip_prefix('#') {
+                Some(header) => grid.start_group(header),
+                None         => grid.add(Cell::from(&*contents)),
+            }
+        }
+
+        grid
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.cells.reserve(additional);
+    }
+
+    /// Gives back any memory the grid holds beyond what its cells need, such
+    /// as after reserving room for more cells than were added, or after
+    /// making cells whose contents were built up with room to spare.
+    pub fn shrink_to_fit(&mut self) {
+        self.cells.shrink_to_fit();
+        self.groups.shrink_to_fit();
+
+        let cells = self.cells.iter_mut()
+            .chain(self.hidden.values_mut())
+            .chain(self.groups.iter_mut().map(|(_, header)| header));
+        for cell in cells {
+            cell.contents.shrink_to_fit();
+        }
+    }
+
+    /// Estimates how many bytes of heap memory the grid holds for its cells
+    /// and their contents, counting the room that’s been allocated rather
+    /// than just the room that’s in use. The grid’s options aren’t counted,
+    /// and nor is the bookkeeping of the maps holding hidden cells and the
+    /// number of cells of each width.
+    pub fn memory_footprint(&self) -> usize {
+        use std::mem::size_of;
+
+        let contents: usize = self.cells.iter()
+            .chain(self.hidden.values())
+            .chain(self.groups.iter().map(|(_, header)| header))
+            .map(|cell| heap_size(&cell.contents))
+            .sum();
+
+        contents
+            + self.cells.capacity() * size_of::<Cell>()
+            + self.width_counts.len() * size_of::<(Width, usize)>()
+            + self.groups.capacity() * size_of::<(usize, Cell)>()
+            + self.hidden.len() * size_of::<(usize, Cell)>()
+    }
+
+    pub fn add(&mut self, cell: Cell) {
+        self.add_unmeasured(self.options.measured(cell));
+    }
+
+    /// Adds a cell if it’s no wider than the options’ maximum cell width,
+    /// or hands it back in an error if it is. Cells of any width are added
+    /// when there’s no maximum.
+    pub fn try_add(&mut self, cell: Cell) -> Result<(), CellTooWide> {
+        let cell = self.options.measured(cell);
+        match self.options.max_cell_width {
+            Some(maximum) if cell.width > maximum  => Err(CellTooWide { cell, maximum }),
+            _                                      => {
+                self.add_unmeasured(cell);
+                Ok(())
+            },
+        }
+    }
+
+    /// Adds a cell without measuring it again, whatever the emoji width
+    /// policy, for cells whose widths the caller has already measured.
+    fn add_unmeasured(&mut self, cell: Cell) {
+        let cell = self.options.aligned(cell);
+        if cell.width > self.widest_cell_length || self.widest_cell_index.is_none() {
+            self.widest_cell_length = cell.width;
+            self.widest_cell_index = Some(self.cells.len());
+        }
+
+        self.count_width(&cell, true);
+        self.cells.push(cell)
+    }
+
+    /// Adds left-aligned cells whose widths have already been measured, as
+    /// with `Cell::raw`, without measuring any of them again.
+    pub fn add_measured<I>(&mut self, cells: I)
+    where I: IntoIterator<Item = (String, Width)>
+    {
+        let cells = cells.into_iter();
+        self.reserve(cells.size_hint().0);
+
+        for (contents, width) in cells {
+            self.add_unmeasured(Cell::raw(contents, width, Alignment::Left));
+        }
+    }
+
+    /// Hides or shows the cell at the given index, so a grid can be
+    /// filtered as the user types without building it up again. Hidden
+    /// cells stay in the grid, but are left out of its layout and rendering,
+    /// with the cells after them moving up to fill the gap.
+    ///
+    /// The index counts every cell added to the grid, whether it’s hidden or
+    /// not. Everything else, including the indices of the cells in a
+    /// `Display`, counts only the cells that are shown, and `original_index`
+    /// converts them back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    ///
+    /// Each call moves the cells after this one along, so to filter every
+    /// cell at once, use `retain`.
+    pub fn set_visible(&mut self, index: usize, visible: bool) {
+        assert!(index < self.cells.len() + self.hidden.len(), "cell index {} out of bounds", index);
+
+        if visible {
+            let Some(cell) = self.hidden.remove(&index) else { return };
+            let position = self.shown_position(index);
+            self.count_width(&cell, true);
+
+            // The cell goes before the widest one if it’s as wide, as the
+            // first of the widest cells is the one that counts.
+            match self.widest_cell_index {
+                Some(widest) if cell.width < self.widest_cell_length
+                             || (cell.width == self.widest_cell_length && position > widest) => {
+                    if position <= widest {
+                        self.widest_cell_index = Some(widest + 1);
+                    }
+                },
+                _ => {
+                    self.widest_cell_length = cell.width;
+                    self.widest_cell_index = Some(position);
+                },
+            }
+
+            self.cells.insert(position, cell);
+        }
+        else {
+            if self.hidden.contains_key(&index) {
+                return;
+            }
+
+            let position = self.shown_position(index);
+            let cell = self.cells.remove(position);
+            self.count_width(&cell, false);
+            self.hidden.insert(index, cell);
+
+            // Only hiding the widest cell means looking for the next widest.
+            match self.widest_cell_index {
+                Some(widest) if widest == position  => self.find_widest_cell(),
+                Some(widest) if widest > position   => self.widest_cell_index = Some(widest - 1),
+                _                                   => {},
+            }
+        }
+
+        // Fewer cells can fit into fewer lines, so the last fit is no help.
+        self.fit_hint = None;
+    }
+
+    /// Shows the cells the function returns `true` for, and hides the rest,
+    /// as with calling `set_visible` for every cell, but in a single pass
+    /// over them. The function is given each cell’s index, counting hidden
+    /// cells, along with the cell itself.
+    pub fn retain(&mut self, mut visible: impl FnMut(usize, &Cell) -> bool) {
+        let num_cells = self.cells.len() + self.hidden.len();
+        let mut shown = std::mem::take(&mut self.cells).into_iter();
+        let mut hidden = std::mem::take(&mut self.hidden).into_iter().peekable();
+
+        self.cells.reserve(num_cells);
+        for index in 0 .. num_cells {
+            let cell = match hidden.next_if(|&(hidden, _)| hidden == index) {
+                Some((_, cell))  => cell,
+                None             => shown.next().expect("every index is either shown or hidden"),
+            };
+
+            if visible(index, &cell) {
+                self.cells.push(cell);
+            }
+            else {
+                self.hidden.insert(index, cell);
+            }
+        }
+
+        self.recount();
+    }
+
+    /// Works out the totals used to fit the grid, and which cell is the
+    /// widest, from scratch, after the shown cells have all been changed.
+    fn recount(&mut self) {
+        self.width_counts.clear();
+        self.width_sum = 0;
+        self.cell_count = 0;
+        for cell in &self.cells {
+            let width = self.options.cell_width(cell);
+            *self.width_counts.entry(width).or_insert(0) += 1;
+            self.width_sum += width;
+            self.cell_count += 1;
+        }
+
+        self.find_widest_cell();
+        self.fit_hint = None;
+    }
+
+    /// Adds a cell’s width to the totals used to fit the grid, or takes it
+    /// away again.
+    fn count_width(&mut self, cell: &Cell, shown: bool) {
+        let width = self.options.cell_width(cell);
+        if shown {
+            *self.width_counts.entry(width).or_insert(0) += 1;
+            self.width_sum += width;
+            self.cell_count += 1;
+        }
+        else {
+            if let Some(count) = self.width_counts.get_mut(&width) {
+                *count -= 1;
+                if *count == 0 {
+                    self.width_counts.remove(&width);
+                }
+            }
+            self.width_sum -= width;
+            self.cell_count -= 1;
+        }
+    }
+
+    /// Looks through the shown cells for the first of the widest ones.
+    fn find_widest_cell(&mut self) {
+        self.widest_cell_index = None;
+        self.widest_cell_length = 0;
+        for (index, cell) in self.cells.iter().enumerate() {
+            if cell.width > self.widest_cell_length || self.widest_cell_index.is_none() {
+                self.widest_cell_length = cell.width;
+                self.widest_cell_index = Some(index);
+            }
+        }
+    }
+
+    /// Returns the index a shown cell was added to the grid at, counting
+    /// hidden cells, from its index among the cells that are shown.
+    pub fn original_index(&self, shown_index: usize) -> usize {
+        let mut index = shown_index;
+        for &hidden in self.hidden.keys() {
+            if hidden > index {
+                break;
+            }
+            index += 1;
+        }
+
+        index
+    }
+
+    /// Returns the position among the shown cells that the cell with the
+    /// given index, counting hidden cells, has or would have.
+    fn shown_position(&self, index: usize) -> usize {
+        index - self.hidden.range(.. index).count()
+    }
+
+    /// Returns the widest cell in the grid, along with its index, so a grid
+    /// that doesn’t fit can say which cell is to blame. If several cells are
+    /// equally wide, the first one is returned.
+    pub fn widest_cell(&self) -> Option<(usize, &Cell)> {
+        self.widest_cell_index.map(|index| (index, &self.cells[index]))
+    }
+
+    /// Makes a quick guess at how many columns the grid would have in the
+    /// given width, from the average width of its cells, without working out
+    /// the layout. This takes time in proportion to the number of columns
+    /// rather than the number of cells, so it can be used to size buffers or
+    /// show progress before running `fit_into_width`.
+    ///
+    /// The guess assumes every column is as wide as the average cell, so
+    /// grids whose cells vary a lot in width usually end up with fewer
+    /// columns than this. The separators, index column, line prefix and
+    /// annotations are counted the same way they are when the grid gets
+    /// fitted. Returns 0 if the grid is empty, or if its widest cell doesn’t
+    /// fit into the width.
+    pub fn estimate_columns(&self, maximum_width: Width) -> usize {
+        let widest = self.fitter().widest_width().unwrap_or(0);
+        if self.cell_count == 0 || widest + self.options.reserved_width() > maximum_width {
+            return 0;
+        }
+
+        if let Some(num_columns) = self.fixed_columns {
+            return num_columns;
+        }
+
+        // Separators that can shrink get as narrow as they’re allowed to when
+        // that fits the grid into fewer lines, so guess that they do.
+        let narrowest;
+        let options = match (&self.options.filling, self.options.min_separator_width, &self.options.gutter) {
+            (Filling::Spaces(_), Some(minimum), None) => {
+                narrowest = GridOptions { filling: Filling::Spaces(minimum), ..self.options.clone() };
+                &narrowest
+            },
+            _ => &self.options,
+        };
+
+        let average_width = self.width_sum.div_ceil(self.cell_count);
+        let fits = |num_columns: usize| {
+            let num_lines = self.cell_count.div_ceil(num_columns);
+            num_columns * average_width + options.separators_width(num_columns)
+                + options.index_width(num_lines) + options.reserved_width() <= maximum_width
+        };
+
+        (2 ..= self.cell_count).take_while(|&num_columns| fits(num_columns)).last().unwrap_or(1)
+    }
+
+    /// Whether the grid has no cells to show, either because none have been
+    /// added or because they’ve all been hidden.
+    pub fn is_empty(&self) -> bool {
+        self.cell_count == 0
+    }
+
+    /// Whether every cell fits side by side on a single line of the given
+    /// width, so a caller can print them inline without fitting the grid
+    /// at all. Unless some columns have separators of their own, this takes
+    /// the same time however many cells there are.
+    ///
+    /// Only the cells and what goes between and before them are counted:
+    /// the separators at their usual width, the line prefix, and the index
+    /// column. Group and column headers aren’t. Grids whose cells come
+    /// arranged into rows only fit if they have a single row. An empty grid
+    /// always fits.
+    pub fn fits_on_one_line(&self, maximum_width: Width) -> bool {
+        if self.fixed_columns.is_some_and(|num_columns| self.cell_count > num_columns) {
+            return false;
+        }
+
+        let width = self.width_sum + self.options.separators_width(self.cell_count)
+            + self.options.index_width(1) + self.options.reserved_width();
+        self.is_empty() || width <= maximum_width
+    }
+
+    /// Starts a new group of cells, shown under a header line that spans the
+    /// width of the grid, like the directory names in `ls -R`. Cells added
+    /// afterwards belong to the group: they start on a new line and flow in
+    /// columns as usual, with every group sharing the same column widths.
+    /// Cells added before the first group are shown above it, without a
+    /// header.
+    ///
+    /// Headers are never truncated, so the grid only fits into widths that
+    /// are at least as wide as its widest header.
+    pub fn start_group(&mut self, header: impl Into<Cell>) {
+        let header = self.options.measured(header.into());
+        self.groups.push((self.cells.len() + self.hidden.len(), header));
+    }
+
+    /// Splits the cells into their groups, returning the header of each
+    /// group, if it has one, along with the indices of its cells.
+    fn groups(&self) -> Vec<(Option<&Cell>, Range<usize>)> {
+        let first_start = self.groups.first().map_or(self.cells.len(), |&(start, _)| self.shown_position(start));
+        let mut groups = Vec::with_capacity(self.groups.len() + 1);
+        if first_start > 0 || self.groups.is_empty() {
+            groups.push((None, 0 .. first_start));
+        }
+
+        for (index, (start, header)) in self.groups.iter().enumerate() {
+            let end = self.groups.get(index + 1).map_or(self.cells.len(), |&(start, _)| self.shown_position(start));
+            groups.push((Some(header), self.shown_position(*start) .. end));
+        }
+
+        groups
+    }
+
+    /// Works out the layout the grid would have in the given width if the
+    /// cell were added to it, without adding it, so it can be compared with
+    /// the current layout before committing to the cell. Returns `None` if
+    /// the grid wouldn’t fit any more.
+    ///
+    /// Only the count of cells of each width gets copied, not the grid, but
+    /// this still takes as long as adding the cell and calling
+    /// `fit_into_width` would.
+    pub fn fit_after_adding(&self, cell: &Cell, maximum_width: Width) -> Option<Layout> {
+        let cell = self.options.aligned(self.options.measured(cell.clone()));
+        let mut width_counts = self.width_counts.clone();
+        *width_counts.entry(self.options.cell_width(&cell)).or_insert(0) += 1;
+
+        let fitter = Fitter {
+            options:       &self.options,
+            separators:    self.options.separators(None),
+            cells:         &Appended { cells: &self.cells, cell: &cell },
+            width_counts:  Cow::Owned(width_counts),
+            cell_count:    self.cell_count + 1,
+        };
+
+        if fitter.widest_too_wide(maximum_width) {
+            return None;
+        }
+
+        if !self.groups.is_empty() {
+            return self.grouped_dimensions(maximum_width, None, Some(&cell));
+        }
+
+        match self.fixed_columns {
+            Some(num_columns)  => fitter.fit_columns(num_columns, maximum_width),
+            None               => fitter.fit(maximum_width),
+        }
+    }
+
+    /// Returns whether the grid would still fit into the given width if the
+    /// cell were added to it.
+    pub fn would_fit(&self, cell: &Cell, maximum_width: Width) -> bool {
+        self.fit_after_adding(cell, maximum_width).is_some()
+    }
+
+    /// Fits the grid into the given width, like `fit_into_width`, but picks
+    /// up where the last call left off. Adding cells to a grid almost never
+    /// lets it fit into fewer lines, so when the width hasn’t changed, the
+    /// search starts at the number of lines the last call found and works
+    /// upwards, instead of going through every possibility again. This makes
+    /// it cheap to re-fit a grid after each cell that gets streamed into it.
+    pub fn current_fit(&mut self, maximum_width: Width) -> Option<Display<'_>> {
+        let dimensions = match self.fit_hint {
+            Some((width, num_lines)) if width == maximum_width
+                                     && self.cell_count > 1
+                                     && self.fixed_columns.is_none()
+                                     && self.groups.is_empty()
+                                     && self.options.layout_score == LayoutScore::FewestLines => {
+                self.fitter().dimensions_from_lines(num_lines.max(1), maximum_width)
+            },
+            _ => self.width_dimensions(maximum_width, None),
+        };
+
+        self.fit_hint = dimensions.as_ref().map(|dims| (maximum_width, dims.num_lines));
+        self.display(dimensions, maximum_width)
+    }
+
+    /// Fits the grid into the given width, like `fit_into_width`, but keeps
+    /// the number of columns the last call used, so a grid that gets re-fit
+    /// whenever a terminal is resized doesn’t get rearranged every time the
+    /// width changes by one. The grid is only rearranged when it no longer
+    /// fits into that many columns, or when it would leave more than `slack`
+    /// characters unused compared to the layout `fit_into_width` picks.
+    pub fn sticky_fit(&mut self, maximum_width: Width, slack: Width) -> Option<Display<'_>> {
+        let best = self.width_dimensions(maximum_width, None);
+
+        let kept = self.sticky_columns
+            .filter(|_| self.cell_count > 1 && self.fixed_columns.is_none())
+            .map(|num_columns| self.layout_for_columns(num_columns))
+            .filter(|dimensions| {
+                let width = self.options.layout_width(dimensions);
+                let best_width = best.as_ref().map_or(0, |best| self.options.layout_width(best));
+                width <= maximum_width && best_width <= width + slack
+            });
+
+        match kept {
+            Some(mut dimensions) => {
+                self.options.spread_room(&mut dimensions, maximum_width);
+                Some(Display {
+                    grid:          self,
+                    dimensions,
+                    maximum_width: Some(maximum_width),
+                    options:       Cow::Borrowed(&self.options),
+                })
+            },
+            None => {
+                self.sticky_columns = best.as_ref().map(|dims| dims.widths.len());
+                self.display(best, maximum_width)
+            },
+        }
+    }
+
+    /// Lays the cells out into the given number of columns, or fewer if
+    /// that many would leave some empty.
+    fn layout_for_columns(&self, num_columns: usize) -> Layout {
+        if !self.groups.is_empty() {
+            return self.grouped_layout(num_columns, None);
+        }
+
+        let num_lines = self.cell_count.div_ceil(num_columns);
+        self.fitter().column_widths(num_lines, self.cell_count.div_ceil(num_lines))
+    }
+
+    pub fn fit_into_width(&self, maximum_width: Width) -> Option<Display<'_>> {
+        self.display(self.width_dimensions(maximum_width, None), maximum_width)
+    }
+
+    /// Fits the grid into each of the given widths, such as the widths a
+    /// window can be resized to, and returns the width that gives the
+    /// densest layout, the one with the smallest product of its width and
+    /// its number of lines, along with the grid fitted into it. Ties go to
+    /// the narrower width. Returns `None` if the grid fits into none of them.
+    ///
+    /// The widths are tried from widest to narrowest, and a narrower width
+    /// never fits the grid into fewer lines than a wider one, so each search
+    /// starts from where the last one finished, as `current_fit` does.
+    pub fn fit_best(&self, widths: &[Width]) -> Option<(Width, Display<'_>)> {
+        let mut widths = widths.to_vec();
+        widths.sort_unstable_by(|a, b| b.cmp(a));
+        widths.dedup();
+
+        let searches_upwards = self.cell_count > 1
+                            && self.fixed_columns.is_none()
+                            && self.groups.is_empty()
+                            && self.options.layout_score == LayoutScore::FewestLines;
+
+        let mut best: Option<(Width, Layout)> = None;
+        let mut fewest_lines = None;
+
+        for width in widths {
+            let dimensions = match fewest_lines {
+                Some(num_lines) if searches_upwards  => self.fitter().dimensions_from_lines(num_lines, width),
+                _                                     => self.width_dimensions(width, None),
+            };
+
+            // Narrower widths won’t fit if this one doesn’t.
+            let Some(dimensions) = dimensions else { break };
+            fewest_lines = Some(dimensions.num_lines.max(1));
+
+            let density = |dims: &Layout| self.options.layout_width(dims) * dims.num_lines;
+            if best.as_ref().is_none_or(|(_, best)| density(&dimensions) <= density(best)) {
+                best = Some((width, dimensions));
+            }
+        }
+
+        let (width, dimensions) = best?;
+        self.display(Some(dimensions), width).map(|display| (width, display))
+    }
+
+    /// Returns the number of rows the grid would have if it were fit into
+    /// the given width, the same as the `row_count` of what `fit_into_width`
+    /// returns, for sizing scrollbars. This works out the width of each
+    /// layout it tries without keeping the widths of its columns, so it
+    /// allocates nothing. Grids with groups, placeholders, labels, a layout
+    /// score, or separators that can shrink are fitted in full instead.
+    pub fn rows_needed(&self, maximum_width: Width) -> Option<usize> {
+        let options = &self.options;
+        if self.cell_count <= 1
+        || !self.groups.is_empty()
+        || self.fixed_columns.is_some()
+        || options.placeholder.is_some()
+        || options.layout_score != LayoutScore::FewestLines
+        || options.index_column.is_some()
+        || options.column_headers.is_some()
+        || options.min_separator_width.is_some() {
+            return self.fit_into_width(maximum_width).map(|display| display.row_count());
+        }
+
+        let fitter = self.fitter();
+        if fitter.widest_too_wide(maximum_width) {
+            return None;
+        }
+
+        let theoretical_max_num_lines = fitter.theoretical_max_num_lines(maximum_width);
+        if theoretical_max_num_lines == 1 {
+            return Some(1);
+        }
+
+        let mut fewest_lines_yet = None;
+        for num_lines in (1 .. theoretical_max_num_lines).rev() {
+            if fitter.fits_into_lines(num_lines, maximum_width) {
+                fewest_lines_yet = Some(num_lines);
+            }
+            else {
+                return fewest_lines_yet;
+            }
+        }
+
+        fewest_lines_yet
+    }
+
+    /// Fits as many cells as possible, from the start of the grid, into a
+    /// viewport of the given width and height, counting every line that
+    /// gets rendered, so the rest can be summed up with a footer such as
+    /// “… and 42 more”. The number of cells is found by a binary search, as
+    /// a grid that fits almost always still fits with fewer cells.
+    pub fn fit_into_rect(&self, maximum_width: Width, maximum_height: usize) -> FitResult {
+        let groups: Vec<(usize, Cell)> = self.groups.iter()
+            .map(|(start, header)| (self.shown_position(*start), header.clone()))
+            .collect();
+
+        // The cells being tried are laid out in one copy of the grid, which
+        // gets cut down or filled back up to the number of cells each time,
+        // and the lines are counted from the layout rather than rendered.
+        let mut shown = Grid::new(self.options.clone());
+        shown.fixed_columns = self.fixed_columns;
+
+        let mut fits = |count: usize| {
+            shown.truncate(count);
+            shown.reserve(count.saturating_sub(shown.cells.len()));
+            for cell in &self.cells[shown.cells.len() .. count] {
+                shown.add(cell.clone());
+            }
+            shown.groups = groups.iter().filter(|(start, _)| *start < count).cloned().collect();
+
+            shown.fit_into_width(maximum_width)
+                 .filter(|display| display.line_count() <= maximum_height)
+                 .map(|display| (display.dimensions.clone(), display.narrowed_options()))
+        };
+
+        let mut count = self.cell_count;
+        let mut fitted = fits(count);
+        if fitted.is_none() {
+
+            // Look for the most cells that fit, knowing that `count` of them
+            // do and `too_many` of them don’t.
+            let mut too_many = count;
+            count = 0;
+            while too_many - count > 1 {
+                let middle = count + (too_many - count) / 2;
+                if fits(middle).is_some() {
+                    count = middle;
+                }
+                else {
+                    too_many = middle;
+                }
+            }
+
+            fitted = fits(count);
+        }
+
+        let (layout, options) = fitted.unwrap_or_else(|| {
+            (Layout { num_lines: 0, widths: Vec::new() }, None)
+        });
+        FitResult { grid: shown, layout, options, maximum_width, hidden: self.cell_count - count }
+    }
+
+    /// Takes shown cells off the end of the grid until there are no more
+    /// than the given number left.
+    fn truncate(&mut self, count: usize) {
+        if count >= self.cells.len() {
+            return;
+        }
+
+        for cell in self.cells.split_off(count) {
+            self.count_width(&cell, false);
+        }
+
+        if self.widest_cell_index.is_some_and(|index| index >= count) {
+            self.find_widest_cell();
+        }
+        self.fit_hint = None;
+    }
+
+    /// Displays the grid with the layout found for the given width, unless
+    /// shrinking the separators lets it fit into fewer lines.
+    fn display(&self, dimensions: Option<Layout>, maximum_width: Width) -> Option<Display<'_>> {
+        if let Some((mut dimensions, options)) = self.narrower_fit(dimensions.as_ref(), maximum_width) {
+            options.spread_room(&mut dimensions, maximum_width);
+            return Some(Display {
+                grid:          self,
+                dimensions,
+                maximum_width: Some(maximum_width),
+                options:       Cow::Owned(options),
+            });
+        }
+
+        dimensions.map(|mut dims| {
+            self.options.spread_room(&mut dims, maximum_width);
+            Display {
+                grid:          self,
+                dimensions:    dims,
+                maximum_width: Some(maximum_width),
+                options:       Cow::Borrowed(&self.options),
+            }
+        })
+    }
+
+    /// Tries narrower separators, down to the grid’s minimum separator
+    /// width, looking for the widest one that lets the grid fit into fewer
+    /// lines than the given layout. The options are only copied once a
+    /// width is found, to be returned alongside the layout.
+    fn narrower_fit(&self, dimensions: Option<&Layout>, maximum_width: Width) -> Option<(Layout, GridOptions)> {
+        let (&Filling::Spaces(spacing), Some(minimum), None) = (&self.options.filling, self.options.min_separator_width, &self.options.gutter) else {
+            return None;
+        };
+
+        let num_lines = dimensions.map_or(usize::MAX, |dims| dims.num_lines);
+        if num_lines <= 1 {
+            return None;
+        }
+
+        (minimum .. spacing).rev().find_map(|narrower| {
+            self.width_dimensions(maximum_width, Some(narrower))
+                .filter(|dims| dims.num_lines < num_lines)
+                .map(|dims| (dims, GridOptions { filling: Filling::Spaces(narrower), ..self.options.clone() }))
+        })
+    }
+
+    /// Picks the layout for the given width, with the spaces between
+    /// columns narrowed to the given spacing if there is one.
+    fn width_dimensions(&self, maximum_width: Width, spacing: Option<Width>) -> Option<Layout> {
+        // The widths have been rounded up to the column granularity and
+        // capped at the maximum column width, so check against them rather
+        // than against the widest cell’s contents.
+        if self.fitter().widest_width().unwrap_or(0) + self.options.reserved_width() > maximum_width {
+            // Largest cell is wider than maximum width; it is impossible to fit.
+            return None;
+        }
+
+        if !self.groups.is_empty() {
+            return self.grouped_dimensions(maximum_width, spacing, None);
+        }
+
+        let fitter = Fitter { separators: self.options.separators(spacing), ..self.fitter() };
+        match self.fixed_columns {
+            Some(num_columns) if self.cell_count > 0  => fitter.fit_columns(num_columns, maximum_width),
+            _                                         => fitter.fit(maximum_width),
+        }
+    }
+
+    /// Returns every way of laying the cells out that fits into the given
+    /// width, in order of increasing number of lines, so callers can pick
+    /// between them using their own criteria.
+    ///
+    /// Unlike `fit_into_width`, this tries every possible number of lines,
+    /// so it takes time proportional to the square of the number of cells.
+    pub fn candidate_layouts(&self, maximum_width: Width) -> Vec<Layout> {
+        if !self.groups.is_empty() && self.cell_count > 0 {
+            return self.grouped_candidates(maximum_width, None, None).collect();
+        }
+
+        if self.fixed_columns.is_some() {
+            return self.width_dimensions(maximum_width, None).into_iter().collect();
+        }
+
+        self.fitter().candidate_layouts(maximum_width)
+    }
+
+    /// A fitter for the grid’s cells, for when they aren’t split into
+    /// groups or arranged into rows.
+    fn fitter(&self) -> Fitter<'_, [Cell]> {
+        Fitter {
+            options:       &self.options,
+            separators:    self.options.separators(None),
+            cells:         &self.cells,
+            width_counts:  Cow::Borrowed(&self.width_counts),
+            cell_count:    self.cell_count,
+        }
+    }
+
+    /// Picks the layout for a grid whose cells are split into groups, with
+    /// the given cell added to the end of the last group if there is one.
+    fn grouped_dimensions(&self, maximum_width: Width, spacing: Option<Width>, added: Option<&Cell>) -> Option<Layout> {
+        if self.groups.iter().any(|(_, header)| header.width + self.options.prefix_width() > maximum_width) {
+            return None;
+        }
+
+        let cell_count = self.cell_count + usize::from(added.is_some());
+        if cell_count == 0 {
+            return Some(Layout { num_lines: 0, widths: Vec::new() });
+        }
+
+        let mut candidates = self.grouped_candidates(maximum_width, spacing, added);
+        if self.options.layout_score == LayoutScore::FewestLines {
+            return candidates.next();
+        }
+
+        let fitter = Fitter { separators: self.options.separators(spacing), cell_count, ..self.fitter() };
+        candidates.min_by_key(|dimensions| (fitter.score(dimensions), fitter.tie_break_rank(dimensions)))
+    }
+
+    /// Returns every way of laying grouped cells out that fits into the
+    /// given width, in order of increasing number of lines. Groups can’t
+    /// share lines, so it’s the number of columns that gets searched, from
+    /// the most downwards. The spaces between columns are narrowed to the
+    /// given spacing if there is one, and the given cell is added to the
+    /// end of the last group if there is one.
+    fn grouped_candidates<'a>(&'a self, maximum_width: Width, spacing: Option<Width>, added: Option<&'a Cell>) -> impl Iterator<Item = Layout> + 'a {
+        let separators = self.options.separators(spacing);
+        let group_sizes = self.group_sizes(added.is_some());
+        let column_counts = match self.fixed_columns {
+            Some(num_columns) => num_columns ..= num_columns,
+            None => {
+                let largest_group = group_sizes.iter().copied().max().unwrap_or(0);
+                1 ..= largest_group
+            },
+        };
+
+        column_counts.rev()
+            .filter(move |&num_columns| self.fixed_columns.is_some()
+                                     || group_sizes.iter().any(|&num_cells| self.uses_every_column(num_cells, num_columns)))
+            .map(move |num_columns| self.grouped_layout(num_columns, added))
+            .filter(move |dimensions| separators.layout_width(dimensions) <= maximum_width)
+    }
+
+    /// The number of cells in each group, counting one more in the last
+    /// group if a cell is about to be added to it.
+    fn group_sizes(&self, adding: bool) -> Vec<usize> {
+        let mut group_sizes: Vec<usize> = self.groups().into_iter().map(|(_, cells)| cells.len()).collect();
+        if let Some(last) = group_sizes.last_mut() {
+            *last += usize::from(adding);
+        }
+
+        group_sizes
+    }
+
+    /// Lays grouped cells out over the given number of columns, with each
+    /// group starting on a new line, and with the given cell added to the
+    /// end of the last group if there is one.
+    fn grouped_layout(&self, num_columns: usize, added: Option<&Cell>) -> Layout {
+        let mut widths: Vec<Width> = repeat_n(0, num_columns).collect();
+        let mut num_lines = 0;
+
+        let groups = self.groups();
+        let last_group = groups.len().saturating_sub(1);
+        for (group, (_, cells)) in groups.into_iter().enumerate() {
+            let added = added.filter(|_| group == last_group);
+            let group_lines = (cells.len() + usize::from(added.is_some())).div_ceil(num_columns);
+
+            for position in 0 .. group_lines * num_columns {
+                let index = match self.options.direction {
+                    Direction::LeftToRight  => position % num_columns,
+                    Direction::TopToBottom  => position / group_lines,
+                };
+
+                let cell = match self.cells[cells.clone()].get(position).or(added.filter(|_| position == cells.len())) {
+                    Some(cell)  => cell,
+                    None        => match &self.options.placeholder {
+                        Some(placeholder)  => placeholder,
+                        None               => continue,
+                    },
+                };
+
+                widths[index] = max(widths[index], self.options.cell_width(cell));
+            }
+
+            num_lines += group_lines;
+        }
+
+        self.options.widen_for_headers(&mut widths);
+        Layout { num_lines, widths }
+    }
+
+    /// Whether a group with the given number of cells has something in
+    /// every one of the given number of columns. If no group does, the same
+    /// layout comes from using fewer columns.
+    fn uses_every_column(&self, num_cells: usize, num_columns: usize) -> bool {
+        match self.options.direction {
+            Direction::LeftToRight  => num_cells >= num_columns,
+            Direction::TopToBottom  => num_cells > 0 && num_cells.div_ceil(num_cells.div_ceil(num_columns)) == num_columns,
+        }
+    }
+}
+
+/// Where a `Fitter` gets the width each cell takes up in the layout from.
+trait CellWidths {
+
+    /// The width each cell takes up in the layout, in the order the cells
+    /// get laid out.
+    fn widths<'a>(&'a self, options: &'a GridOptions) -> impl Iterator<Item = Width> + 'a;
+}
+
+impl CellWidths for [Cell] {
+    fn widths<'a>(&'a self, options: &'a GridOptions) -> impl Iterator<Item = Width> + 'a {
+        self.iter().map(move |cell| options.cell_width(cell))
+    }
+}
+
+/// Widths that have already been worked out, so each is only worked out
+/// once however many layouts are tried.
+impl CellWidths for [Width] {
+    fn widths<'a>(&'a self, _options: &'a GridOptions) -> impl Iterator<Item = Width> + 'a {
+        self.iter().copied()
+    }
+}
+
+/// A grid’s cells followed by a cell that hasn’t been added to it yet.
+struct Appended<'a> {
+    cells: &'a [Cell],
+    cell: &'a Cell,
+}
+
+impl CellWidths for Appended<'_> {
+    fn widths<'a>(&'a self, options: &'a GridOptions) -> impl Iterator<Item = Width> + 'a {
+        self.cells.widths(options).chain(once(options.cell_width(self.cell)))
+    }
+}
+
+/// Lays out cells that aren’t split into groups or arranged into rows,
+/// knowing nothing about them but the width each one takes up. Grids fit
+/// their own cells with it, and `layout_cells` fits cells kept somewhere
+/// else without copying them into a grid.
+struct Fitter<'a, W: ?Sized> {
+    options: &'a GridOptions,
+    separators: Separators<'a>,
+    cells: &'a W,
+
+    /// How many cells take up each width in the layout.
+    width_counts: Cow<'a, BTreeMap<Width, usize>>,
+
+    cell_count: usize,
+}
+
+impl<W: CellWidths + ?Sized> Fitter<'_, W> {
+
+    /// The width each cell takes up in the layout, widest first.
+    fn widths_widest_first(&self) -> impl Iterator<Item = Width> + '_ {
+        self.width_counts.iter().rev().flat_map(|(&width, &count)| repeat_n(width, count))
+    }
+
+    /// The width the widest cell takes up in the layout, if there are cells.
+    fn widest_width(&self) -> Option<Width> {
+        self.width_counts.keys().next_back().copied()
+    }
+
+    /// Whether the widest cell is too wide for the given width once the
+    /// line prefix and annotations have taken their share of it.
+    fn widest_too_wide(&self, maximum_width: Width) -> bool {
+        self.widest_width().is_some_and(|widest| widest + self.options.reserved_width() > maximum_width)
+    }
+
+    /// Lays the cells out into the given number of columns, or returns
+    /// `None` if that’s too wide for the given width.
+    fn fit_columns(&self, num_columns: usize, maximum_width: Width) -> Option<Layout> {
+        let num_lines = self.cell_count.div_ceil(num_columns);
+        Some(self.column_widths(num_lines, num_columns))
+            .filter(|dims| self.separators.layout_width(dims) <= maximum_width)
+    }
+
+    /// Picks the layout for the cells in the given width, or returns `None`
+    /// if they don’t fit.
+    fn fit(&self, maximum_width: Width) -> Option<Layout> {
+        if self.widest_width().unwrap_or(0) + self.options.reserved_width() > maximum_width {
+            // Largest cell is wider than maximum width; it is impossible to fit.
+            return None;
+        }
+
+        if self.cell_count == 0 {
+            return Some(Layout { num_lines: 0, widths: Vec::new() });
+        }
+
+        if self.cell_count == 1 {
+            return Some(self.column_widths(1, 1))
+                .filter(|dims| self.separators.layout_width(dims) <= maximum_width);
+        }
+
+        // Labelling the rows or columns takes up a different amount of room
+        // for each number of lines, so every possibility has to be tried.
+        if self.options.layout_score != LayoutScore::FewestLines
+        || self.options.index_column.is_some()
+        || self.options.column_headers.is_some() {
+            return self.candidate_layouts(maximum_width).into_iter()
+                       .min_by_key(|dimensions| (self.score(dimensions), self.tie_break_rank(dimensions)));
+        }
+
+        let theoretical_max_num_lines = self.theoretical_max_num_lines(maximum_width);
+        if theoretical_max_num_lines == 1 {
+            return Some(Layout {
+                num_lines: 1,
+                widths: self.cells.widths(self.options).collect()
+            });
+        }
+
+        let mut smallest_dimensions_yet = None;
+        for num_lines in (1 .. theoretical_max_num_lines).rev() {
+            match self.dimensions_for_lines(num_lines, maximum_width) {
+                Some(dimensions) => smallest_dimensions_yet = Some(dimensions),
+                None             => return smallest_dimensions_yet,
+            }
+        }
+
+        smallest_dimensions_yet
+    }
+
+    /// Lays the cells out over the given number of lines, returning the
+    /// dimensions if the result fits into the width.
+    fn dimensions_for_lines(&self, num_lines: usize, maximum_width: Width) -> Option<Layout> {
+
+        // The number of columns is the number of cells divided by the number
+        // of lines, *rounded up*.
+        let mut num_columns = self.cell_count / num_lines;
+        if !self.cell_count.is_multiple_of(num_lines) {
+            num_columns += 1;
+        }
+        let total_separator_width = self.separators.separators_width(num_columns);
+        if maximum_width < total_separator_width {
+            return None;
+        }
+
+        // Remove the separator width from the available space.
+        let adjusted_width = maximum_width - total_separator_width;
+
+        let potential_dimensions = self.column_widths(num_lines, num_columns);
+        if potential_dimensions.widths.iter().sum::<Width>() + self.separators.index_width(num_lines) + self.options.reserved_width() < adjusted_width {
+            Some(potential_dimensions)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Returns every way of laying the cells out that fits into the given
+    /// width, in order of increasing number of lines.
+    fn candidate_layouts(&self, maximum_width: Width) -> Vec<Layout> {
+        if self.cell_count <= 1 {
+            return self.fit(maximum_width).into_iter().collect();
+        }
+
+        (1 ..= self.cell_count)
+            .filter(|&num_lines| self.uses_every_line(num_lines))
+            .filter_map(|num_lines| self.dimensions_for_lines(num_lines, maximum_width))
+            .collect()
+    }
+
+    /// Finds the fewest lines, starting from the given number, that the
+    /// cells fit into.
+    fn dimensions_from_lines(&self, num_lines: usize, maximum_width: Width) -> Option<Layout> {
+        if self.widest_too_wide(maximum_width) {
+            return None;
+        }
+
+        (num_lines ..= self.cell_count)
+            .filter(|&num_lines| self.uses_every_line(num_lines))
+            .find_map(|num_lines| self.dimensions_for_lines(num_lines, maximum_width))
+    }
+
+    /// Whether the cells fit into the width when laid out over the given
+    /// number of lines, as `dimensions_for_lines` would find, but going down
+    /// each column in turn rather than keeping every column’s width.
+    fn fits_into_lines(&self, num_lines: usize, maximum_width: Width) -> bool {
+        let num_columns = self.cell_count.div_ceil(num_lines);
+        let mut total_width = self.separators.separators_width(num_columns) + self.options.reserved_width();
+
+        for x in 0 .. num_columns {
+            let column_width = match self.options.direction {
+                Direction::LeftToRight  => self.cells.widths(self.options).skip(x).step_by(num_columns).max(),
+                Direction::TopToBottom  => self.cells.widths(self.options).skip(x * num_lines).take(num_lines).max(),
+            };
+
+            total_width += column_width.unwrap_or(0);
+            if total_width >= maximum_width {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn column_widths(&self, num_lines: usize, num_columns: usize) -> Layout {
+        let mut widths: Vec<Width> = repeat_n(0, num_columns).collect();
+        for (index, width) in self.cells.widths(self.options).enumerate() {
+            let index = match self.options.direction {
+                Direction::LeftToRight  => index % num_columns,
+                Direction::TopToBottom  => index / num_lines,
+            };
+            widths[index] = max(widths[index], width);
+        }
+
+        if let Some(placeholder) = &self.options.placeholder {
+            for num in self.cell_count .. num_lines * num_columns {
+                let index = match self.options.direction {
+                    Direction::LeftToRight  => num % num_columns,
+                    Direction::TopToBottom  => num / num_lines,
+                };
+                widths[index] = max(widths[index], self.options.cell_width(placeholder));
+            }
+        }
+
+        self.options.widen_for_headers(&mut widths);
+        Layout { num_lines, widths }
+    }
+
+    fn theoretical_max_num_lines(&self, maximum_width: usize) -> usize {
+        let maximum_width = maximum_width.saturating_sub(self.options.reserved_width());
+        let mut theoretical_min_num_cols = 0;
+        let mut widths_so_far = 0;
+        let mut separators_so_far = 0;
+
+        for (index, width) in self.widths_widest_first().enumerate() {
+            widths_so_far += width;
+
+            // A gutter changes the width of every separator as columns get
+            // added, so they have to be added up again each time.
+            let separators = match self.options.gutter {
+                Some(_)  => self.separators.separators_width(index + 1),
+                None     => separators_so_far,
+            };
+
+            if widths_so_far + separators <= maximum_width {
+                theoretical_min_num_cols += 1;
+            } else {
+                let mut theoretical_max_num_lines = self.cell_count / theoretical_min_num_cols;
+                if !self.cell_count.is_multiple_of(theoretical_min_num_cols) {
+                    theoretical_max_num_lines += 1;
+                }
+                return theoretical_max_num_lines;
+            }
+            separators_so_far += self.separators.separator(index, self.cell_count).width()
+        }
+        1
+    }
+
+    /// Scores a layout using the options’ layout score, where lower is better.
+    fn score(&self, dimensions: &Layout) -> usize {
+        match &self.options.layout_score {
+            LayoutScore::FewestLines      => dimensions.num_lines,
+            LayoutScore::NarrowestWidth   => self.separators.layout_width(dimensions),
+            LayoutScore::Densest          => self.separators.layout_width(dimensions) * dimensions.num_lines,
+            LayoutScore::BalancedColumns  => dimensions.num_lines * dimensions.widths.len() - self.cell_count,
+            LayoutScore::Custom(score)    => (score.0)(dimensions),
+        }
+    }
+
+    /// Ranks layouts with the same score, where lower is better. Layouts
+    /// with the same rank are left in the order they were found.
+    fn tie_break_rank(&self, dimensions: &Layout) -> usize {
+        match self.options.tie_break {
+            TieBreak::FirstFit => 0,
+            TieBreak::WidestColumnLast => {
+                let widest = dimensions.widths.iter().max();
+                if dimensions.widths.last() == widest { 0 } else { 1 }
+            },
+        }
+    }
+
+    /// Whether laying the cells out over the given number of lines leaves
+    /// no line empty. Cells flowing from left to right over too many lines
+    /// run out before the last one, giving the same arrangement as a layout
+    /// with fewer lines.
+    fn uses_every_line(&self, num_lines: usize) -> bool {
+        match self.options.direction {
+            Direction::LeftToRight => {
+                let num_columns = self.cell_count.div_ceil(num_lines);
+                self.cell_count.div_ceil(num_columns) == num_lines
+            },
+            Direction::TopToBottom => true,
+        }
+    }
+}
+
+/// Works out the layout that `Grid::fit_into_width` would pick for the
+/// given cells, for callers that keep their cells somewhere other than a
+/// grid. Only the width of each cell is used, so nothing gets copied into
+/// a grid. The separators are taken as they are, without being narrowed to
+/// the options’ minimum separator width.
+pub fn layout_cells(cells: &[Cell], options: &GridOptions, maximum_width: Width) -> Option<Layout> {
+    let mut width_counts = BTreeMap::new();
+    let widths: Vec<Width> = cells.iter().map(|cell| {
+        let width = match options.emoji_width {
+            EmojiWidth::Measured  => cell.width,
+            policy                => policy.width(&cell.contents),
+        };
+        let width = options.width_in_layout(&cell.contents, width);
+        *width_counts.entry(width).or_insert(0) += 1;
+        width
+    }).collect();
+
+    let fitter = Fitter {
+        options,
+        separators:    options.separators(None),
+        cells:         widths.as_slice(),
+        width_counts:  Cow::Owned(width_counts),
+        cell_count:    cells.len(),
+    };
+
+    fitter.fit(maximum_width)
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Display<'grid> {
+    grid: &'grid Grid,
+    dimensions: Layout,
+
+    /// The width the grid was fitted into, if it was, used to align it.
+    maximum_width: Option<Width>,
+
+    /// The options to display the grid with. These are the grid’s own,
+    /// unless its separators had to shrink to fit it into the width.
+    options: Cow<'grid, GridOptions>,
+}
+
+impl<'grid> Display<'grid> {
+
+    /// Displays a grid using a layout that has already been worked out,
+    /// such as one from an earlier call to `fit_into_width` or one of the
+    /// `candidate_layouts`. Returns `None` if the layout doesn’t match the
+    /// grid: it has to have as many columns as the grid would have when its
+    /// cells are spread over that many lines, and no more lines than the
+    /// grid has cells. Cells wider than their column in the layout are
+    /// truncated or wrapped, as with a maximum column width.
+    pub fn from_layout(grid: &'grid Grid, layout: Layout) -> Option<Self> {
+        // Layouts can come from bytes saved somewhere else, so a line count
+        // in the millions has to be turned away rather than rendered.
+        if layout.num_lines > grid.cell_count {
+            return None;
+        }
+
+        if !grid.groups.is_empty() {
+            let num_columns = layout.widths.len();
+            let expected_lines = match num_columns {
+                0 if grid.cell_count == 0  => 0,
+                0                          => return None,
+                _ => grid.groups().iter().map(|(_, cells)| cells.len().div_ceil(num_columns)).sum(),
+            };
+
+            if layout.num_lines != expected_lines {
+                return None;
+            }
+
+            return Some(Self { grid, dimensions: layout, maximum_width: None, options: Cow::Borrowed(&grid.options) });
+        }
+
+        let expected_columns = match (grid.fixed_columns, layout.num_lines) {
+            _ if grid.cell_count == 0  => 0,
+            (_, 0)                     => return None,
+            (Some(num_columns), _)     => num_columns,
+            (None, num_lines)          => grid.cell_count.div_ceil(num_lines),
+        };
+
+        let expected_lines = match grid.fixed_columns {
+            Some(num_columns)  => grid.cell_count.div_ceil(num_columns),
+            None               => layout.num_lines,
+        };
+
+        if layout.widths.len() != expected_columns || layout.num_lines != expected_lines {
+            return None;
+        }
+
+        Some(Self { grid, dimensions: layout, maximum_width: None, options: Cow::Borrowed(&grid.options) })
+    }
+
+    /// Returns the layout the grid is being displayed with, so it can be
+    /// kept and used again with `Display::from_layout`.
+    pub fn layout(&self) -> &Layout {
+        &self.dimensions
+    }
+
+    /// Lays the cells out in the other direction, for toggling between
+    /// them, without searching for a new layout. Cells that went down the
+    /// columns go across the rows instead, in the same order, keeping the
+    /// same number of columns; cells that went across the rows go down the
+    /// columns, keeping the same number of lines. Only the widths of the
+    /// columns get worked out again.
+    ///
+    /// Returns `None` if the grid, laid out the other way, is wider than
+    /// the width it was fitted into, or if its cells were arranged into
+    /// rows already.
+    pub fn transpose(&self) -> Option<Display<'grid>> {
+        if self.grid.fixed_columns.is_some() {
+            return None;
+        }
+
+        let mut options = self.options.clone().into_owned();
+        options.direction = match options.direction {
+            Direction::LeftToRight  => Direction::TopToBottom,
+            Direction::TopToBottom  => Direction::LeftToRight,
+        };
+
+        // Grouped grids always have as many lines as their groups need.
+        let Layout { num_lines, widths } = &self.dimensions;
+        let cell_count = self.grid.cell_count;
+        let (num_lines, num_columns) = match options.direction {
+            _ if !self.grid.groups.is_empty() || cell_count == 0  => (*num_lines, widths.len()),
+            Direction::LeftToRight                              => (cell_count.div_ceil(widths.len()), widths.len()),
+            Direction::TopToBottom                              => (*num_lines, cell_count.div_ceil(*num_lines)),
+        };
+
+        let mut display = Display {
+            grid:          self.grid,
+            dimensions:    Layout { num_lines, widths: vec![ 0; num_columns ] },
+            maximum_width: self.maximum_width,
+            options:       Cow::Owned(options),
+        };
+
+        let mut widths = vec![ 0; num_columns ];
+        for (x, width) in widths.iter_mut().enumerate() {
+            for y in 0 .. num_lines {
+                let cell = match display.cell_index(y, x) {
+                    Some(index)  => &self.grid.cells[index],
+                    None         => match &display.options.placeholder {
+                        Some(placeholder)  => placeholder,
+                        None               => continue,
+                    },
+                };
+
+                *width = max(*width, display.options.cell_width(cell));
+            }
+        }
+
+        for (x, header) in display.column_headers().into_iter().flatten().enumerate() {
+            widths[x] = max(widths[x], display.options.cell_width(&header));
+        }
+
+        display.dimensions.widths = widths;
+        match self.maximum_width {
+            Some(maximum_width) if display.width() > maximum_width  => None,
+            _                                                       => Some(display),
+        }
+    }
+
+    pub fn width(&self) -> Width {
+        let natural_width = self.natural_width();
+        natural_width + self.justified_gaps(natural_width).iter().sum::<Width>()
+    }
+
+    /// The width of the grid before any justification.
+    fn natural_width(&self) -> Width {
+        let widest_header = self.grid.groups.iter().map(|(_, header)| header.width).max().unwrap_or(0);
+        max(self.options.layout_width(&self.dimensions), self.options.prefix_width() + widest_header)
+    }
+
+    /// The number of spaces added around the separator after each column,
+    /// to spread a justified grid out across the width it was fitted into,
+    /// given the grid’s natural width. Gaps further left get any spaces that
+    /// can’t be shared out evenly, and the last column has no gap after it.
+    fn justified_gaps(&self, natural_width: Width) -> Vec<Width> {
+        let num_columns = self.dimensions.widths.len();
+        let gaps = num_columns.saturating_sub(1);
+        let Some(maximum_width) = self.maximum_width.filter(|_| self.options.justify && gaps > 0) else {
+            return vec![ 0; num_columns ];
+        };
+
+        let room = maximum_width.saturating_sub(natural_width);
+        (0 .. num_columns)
+            .map(|x| if x < gaps { room / gaps + usize::from(x < room % gaps) } else { 0 })
+            .collect()
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.dimensions.num_lines
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.dimensions.widths.iter().all(|&x| x > 0)
+    }
+
+    /// Returns the number of spaces each line is indented by to align the
+    /// grid within the width it was fitted into.
+    fn margin(&self) -> Width {
+        let Some(maximum_width) = self.maximum_width else { return 0 };
+        let room = maximum_width.saturating_sub(self.width());
+
+        match self.options.horizontal_alignment {
+            GridAlign::Left    => 0,
+            GridAlign::Center  => room / 2,
+            GridAlign::Right   => room,
+        }
+    }
+
+    /// Returns an iterator over the visual rows of the grid, yielding the
+    /// row index along with the cells on that row, from left to right.
+    ///
+    /// A row may hold fewer cells than there are columns if the grid ends
+    /// part-way through it.
+    pub fn rows(&self) -> Rows<'_, 'grid> {
+        Rows { display: self, y: 0 }
+    }
+
+    /// Draws the layout as plain ASCII art, for tests and bug reports: a
+    /// line giving the number of lines and the column widths, followed by
+    /// the rows with `|` marking the edges of each column and `·` in place
+    /// of padding. Separators, headers, and index columns are left out, so
+    /// only the columns themselves are shown.
+    pub fn debug_render(&self) -> String {
+        let num_columns = self.dimensions.widths.len();
+        let mut rendered = format!("{} lines, widths {:?}\n", self.dimensions.num_lines, self.dimensions.widths);
+
+        for y in 0 .. self.dimensions.num_lines {
+            let cells: Vec<Option<&Cell>> = (0 .. num_columns)
+                .map(|x| self.cell_index(y, x).map(|index| &self.grid.cells[index]))
+                .collect();
+
+            let lines: Vec<Vec<(Cow<'_, str>, Width)>> = cells.iter().enumerate()
+                .map(|(x, cell)| cell.map_or_else(Vec::new, |cell| self.cell_lines(cell, x)))
+                .collect();
+
+            let height = lines.iter().map(Vec::len).max().unwrap_or(0).max(1);
+
+            for line in 0 .. height {
+                rendered.push('|');
+                for (x, cell) in cells.iter().enumerate() {
+                    let column_width = self.dimensions.widths[x];
+                    match (cell, line_of(&cells, &lines, x, line, height)) {
+                        (Some(cell), Some((contents, width)))  => rendered.push_str(&pad_string(contents, column_width.saturating_sub(*width), cell.alignment, '·')),
+                        (Some(_), None)                        => rendered.extend(repeat_n('·', column_width)),
+                        (None, _)                              => rendered.push_str(&spaces(column_width)),
+                    }
+                    rendered.push('|');
+                }
+                rendered.push('\n');
+            }
+        }
+
+        rendered
+    }
+
+    /// Describes the layout as JSON, for frontends that draw the grid
+    /// themselves, in the form
+    /// `{"rows": 2, "column_widths": [4, 5], "cells": [{"row": 0, "col": 0, "contents": "..."}, ...]}`.
+    /// Cells are listed row by row, and hold their full contents, even if
+    /// they would be truncated or wrapped when rendered.
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let num_columns = self.dimensions.widths.len();
+        let cells: Vec<serde_json::Value> = (0 .. self.dimensions.num_lines)
+            .flat_map(|y| (0 .. num_columns).map(move |x| (y, x)))
+            .filter_map(|(y, x)| self.cell_index(y, x).map(|index| (y, x, &self.grid.cells[index])))
+            .map(|(y, x, cell)| serde_json::json!({ "row": y, "col": x, "contents": &cell.contents[..] }))
+            .collect();
+
+        serde_json::json!({
+            "rows": self.dimensions.num_lines,
+            "column_widths": self.dimensions.widths,
+            "cells": cells,
+        })
+    }
+
+    /// Draws the grid into a frame of characters, such as the back buffer of
+    /// a terminal UI, instead of producing newline-terminated text.
+    ///
+    /// The frame is a row-major buffer `frame_width` characters wide, and the
+    /// grid’s top-left corner is placed at `origin`, given as `(x, y)`.
+    /// Anything that would land outside the frame is clipped. Characters
+    /// that take up two columns are written to the first, and the second is
+    /// set to `'\0'` so the caller knows to skip over it. Positions past the
+    /// end of each rendered line are left as they were.
+    pub fn render_to_frame(&self, frame: &mut [char], frame_width: Width, origin: (usize, usize)) {
+        if frame_width == 0 {
+            return;
+        }
+
+        let frame_height = frame.len() / frame_width;
+        let (origin_x, origin_y) = origin;
+        let rendered = self.to_string();
+
+        for (line_number, line) in rendered.lines().enumerate() {
+            let y = origin_y + line_number;
+            if y >= frame_height {
+                break;
+            }
+
+            let row = &mut frame[y * frame_width .. (y + 1) * frame_width];
+            let mut x = origin_x;
+            for c in line.chars() {
+                let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+                if char_width == 0 {
+                    continue;
+                }
+                else if x + char_width > frame_width {
+                    break;
+                }
+
+                row[x] = c;
+                for continuation in &mut row[x + 1 .. x + char_width] {
+                    *continuation = '\0';
+                }
+
+                x += char_width;
+            }
+        }
+    }
+
+    /// Splits a cell into the lines it takes up when displayed in the given
+    /// column, shortening or wrapping it according to the grid’s overflow
+    /// policy if it’s too wide.
+    fn cell_lines<'a>(&self, cell: &'a Cell, x: usize) -> Vec<(Cow<'a, str>, Width)> {
+        let width = self.dimensions.widths[x];
+        if cell.width <= width {
+            return vec![ (Cow::Borrowed(&cell.contents[..]), cell.width) ];
+        }
+
+        match self.options.overflow {
+            Overflow::Truncate => {
+                let (contents, width) = truncate_string(&cell.contents, width, self.options.truncate_side, self.options.emoji_width);
+                vec![ (Cow::Owned(contents), width) ]
+            },
+            Overflow::Wrap => {
+                wrap_string(&cell.contents, width, self.options.emoji_width).into_iter()
+                    .map(|(contents, width)| (Cow::Owned(contents), width))
+                    .collect()
+            },
+        }
+    }
+
+    /// Returns the index of the cell at the given row and column, following
+    /// the grid’s direction, or `None` if the grid ends before that position.
+    fn cell_index(&self, y: usize, x: usize) -> Option<usize> {
+        if !self.grid.groups.is_empty() {
+            return self.grouped_cell_index(y, x);
+        }
+
+        let num = match self.options.direction {
+            Direction::LeftToRight  => y * self.dimensions.widths.len() + x,
+            Direction::TopToBottom  => y + self.dimensions.num_lines * x,
+        };
+
+        if num < self.grid.cells.len() { Some(num) } else { None }
+    }
+
+    /// Returns the index of the cell at the given row and column of a grid
+    /// whose cells are split into groups, where each group starts on a new
+    /// row.
+    fn grouped_cell_index(&self, mut y: usize, x: usize) -> Option<usize> {
+        let num_columns = self.dimensions.widths.len();
+        if num_columns == 0 {
+            return None;
+        }
+
+        for (_, cells) in self.grid.groups() {
+            let group_lines = cells.len().div_ceil(num_columns);
+            if y >= group_lines {
+                y -= group_lines;
+                continue;
+            }
+
+            let position = match self.options.direction {
+                Direction::LeftToRight  => y * num_columns + x,
+                Direction::TopToBottom  => y + group_lines * x,
+            };
+
+            return if position < cells.len() { Some(cells.start + position) } else { None };
+        }
+
+        None
+    }
+
+    /// Makes the header for each column, if the grid has column headers.
+    fn column_headers(&self) -> Option<Vec<Cell>> {
+        self.options.column_headers.is_some()
+            .then(|| (0 .. self.dimensions.widths.len()).map(|x| self.options.column_header(x)).collect())
+    }
+
+    /// The number of lines the column headers take up, which is as many as
+    /// the tallest of them takes, or none if there aren’t any.
+    fn column_header_height(&self) -> usize {
+        self.column_headers().map_or(0, |column_headers| {
+            column_headers.iter().enumerate()
+                          .map(|(x, header)| self.cell_lines(header, x).len())
+                          .max().unwrap_or(0).max(1)
+        })
+    }
+
+    /// The number of lines the row with the given index takes up, which is
+    /// as many as its tallest cell or placeholder takes.
+    fn row_height(&self, y: usize) -> usize {
+        (0 .. self.dimensions.widths.len())
+            .filter_map(|x| match self.cell_index(y, x) {
+                Some(index)  => Some(self.cell_lines(&self.grid.cells[index], x).len()),
+                None         => self.options.placeholder.as_ref().map(|placeholder| self.cell_lines(placeholder, x).len()),
+            })
+            .max().unwrap_or(0).max(1)
+    }
+
+    /// The number of lines the grid takes up when it’s rendered, headers
+    /// included, worked out from the layout without rendering it.
+    fn line_count(&self) -> usize {
+        let num_columns = self.dimensions.widths.len();
+        let header_lines = self.headers().len();
+
+        // Plain output has a line for each cell and each column header.
+        if self.options.output_mode == OutputMode::Plain {
+            let column_header_lines = if self.options.column_headers.is_some() { num_columns } else { 0 };
+            let cell_lines: usize = (0 .. self.dimensions.num_lines)
+                .map(|y| (0 .. num_columns).filter(|&x| self.cell_index(y, x).is_some()).count())
+                .sum();
+            return column_header_lines + header_lines + cell_lines;
+        }
+
+        let row_lines: usize = (0 .. self.dimensions.num_lines).map(|y| self.row_height(y)).sum();
+        self.column_header_height() + header_lines + row_lines
+    }
+
+    /// Returns the options the grid is displayed with if they differ from
+    /// the grid’s own, because its separators shrank.
+    fn narrowed_options(&self) -> Option<GridOptions> {
+        match &self.options {
+            Cow::Owned(options)  => Some(options.clone()),
+            Cow::Borrowed(_)     => None,
+        }
+    }
+
+    /// Returns the header of each group, along with the row it goes above.
+    /// Headers of groups with no cells go above the same row as the next
+    /// group’s, or after the last row.
+    fn headers(&self) -> Vec<(usize, &'grid Cell)> {
+        let num_columns = self.dimensions.widths.len();
+        let mut headers = Vec::with_capacity(self.grid.groups.len());
+        let mut y = 0;
+
+        for (header, cells) in self.grid.groups() {
+            if let Some(header) = header {
+                headers.push((y, header));
+            }
+
+            if num_columns > 0 {
+                y += cells.len().div_ceil(num_columns);
+            }
+        }
+
+        headers
+    }
+
+    /// Writes a group header on a line of its own.
+    fn write_header(&self, f: &mut dyn StyledWrite, header: &Cell, margin: &str) -> fmt::Result {
+        let padding = self.width().saturating_sub(header.width);
+        match header.alignment {
+            _ if self.options.output_mode == OutputMode::Plain  => writeln!(f, "{}", header.contents),
+            _ if self.options.pad_lines                         => writeln!(f, "{}{}", margin, pad_string(&header.contents, padding, header.alignment, header.pad_char)),
+            alignment                                           => writeln!(f, "{}{}", margin, pad_string_trimmed(&header.contents, padding, alignment, header.pad_char)),
+        }
+    }
+
+    /// Works out where each cell ends up when the grid is rendered, in
+    /// columns and lines from the top-left corner. A cell’s area covers its
+    /// whole column, padding included, and every line of its row.
+    #[cfg_attr(not(feature = "ratatui"), allow(dead_code))]
+    fn cell_areas(&self) -> Vec<CellArea> {
+        let mut areas = Vec::with_capacity(self.grid.cells.len());
+        let headers = self.headers();
+
+        let gaps = self.justified_gaps(self.natural_width());
+        let start = self.options.prefix_width() + self.margin() + self.options.index_width(self.dimensions.num_lines);
+        let mut line = self.column_header_height();
+
+        for y in 0 .. self.dimensions.num_lines {
+            line += headers.iter().filter(|&&(row, _)| row == y).count();
+            let mut height = 1;
+            let mut offset = start;
+
+            for (x, &width) in self.dimensions.widths.iter().enumerate() {
+                if let Some(index) = self.cell_index(y, x) {
+                    height = max(height, self.cell_lines(&self.grid.cells[index], x).len());
+                    areas.push(CellArea { index, x: offset, y: line, width, height: 0 });
+                }
+
+                offset += width + self.options.separator(x, self.dimensions.widths.len()).width() + gaps[x];
+            }
+
+            for area in areas.iter_mut().rev().take_while(|area| area.y == line) {
+                area.height = height;
+            }
+
+            line += height;
+        }
+
+        areas
+    }
+}
+
+/// The cells that fit into a viewport, found by `Grid::fit_into_rect`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FitResult {
+
+    /// A copy of the grid with only the cells that fit.
+    grid: Grid,
+    layout: Layout,
+
+    /// The options the cells are displayed with, if the separators had to
+    /// shrink to fit them.
+    options: Option<GridOptions>,
+
+    maximum_width: Width,
+    hidden: usize,
+}
+
+impl FitResult {
+
+    /// Displays the cells that fit.
+    pub fn display(&self) -> Display<'_> {
+        Display {
+            grid:          &self.grid,
+            dimensions:    self.layout.clone(),
+            maximum_width: Some(self.maximum_width),
+            options:       Cow::Borrowed(self.options.as_ref().unwrap_or(&self.grid.options)),
+        }
+    }
+
+    /// Returns the number of cells that fit, from the start of the grid.
+    pub fn shown(&self) -> usize {
+        self.grid.cell_count
+    }
+
+    /// Returns the number of cells that didn’t fit.
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+}
+
+/// The part of a rendered grid taken up by one cell.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct CellArea {
+    index: usize,
+    x: Width,
+    y: usize,
+    width: Width,
+    height: usize,
+}
+
+impl<'a, 'grid> IntoIterator for &'a Display<'grid> {
+    type Item = (usize, Vec<&'grid Cell>);
+    type IntoIter = Rows<'a, 'grid>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows()
+    }
+}
+
+/// Iterator over the rows of a `Display`, created by `Display::rows`.
+#[derive(Debug)]
+pub struct Rows<'a, 'grid> {
+    display: &'a Display<'grid>,
+    y: usize,
+}
+
+impl<'grid> Iterator for Rows<'_, 'grid> {
+    type Item = (usize, Vec<&'grid Cell>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.y >= self.display.dimensions.num_lines {
+            return None;
+        }
+
+        let y = self.y;
+        self.y += 1;
+
+        let grid = self.display.grid;
+        let cells = (0 .. self.display.dimensions.widths.len())
+            .filter_map(|x| self.display.cell_index(y, x))
+            .map(|num| &grid.cells[num])
+            .collect();
+
+        Some((y, cells))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.display.dimensions.num_lines - self.y;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Rows<'_, '_> {}
+
+
+impl fmt::Display for Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        self.write_to(f, &[], 0 .. self.dimensions.num_lines, None)
+    }
+}
+
+impl Display<'_> {
+
+    /// Renders the grid, surrounding the cells covered by the given spans
+    /// with their styling strings, such as the escape codes to turn reverse
+    /// video on and off to show a selection. The strings go around each
+    /// cell’s padded contents, but not the separators between cells, so
+    /// they’re drawn as if every cell were as wide as its column.
+    ///
+    /// Cells covered by more than one span use the last one. The styling
+    /// strings are taken to have no width.
+    pub fn render_with_spans(&self, spans: &[Span<'_>]) -> String {
+        let mut rendered = String::new();
+        self.write_to(&mut rendered, spans, 0 .. self.dimensions.num_lines, None).expect("writing to a String cannot fail");
+        rendered
+    }
+
+    /// Renders the grid in a form that’s guaranteed not to change between
+    /// patch releases, for tests that compare output byte for byte. It’s
+    /// the grid as it’s usually displayed, except that no line ends in
+    /// spaces, so changes to how the ends of lines are padded don’t show.
+    /// Every line, including the last, ends with a single `\n`, and a grid
+    /// with nothing to show renders as an empty string.
+    ///
+    /// Changes to this output only come with a new minor version, and the
+    /// golden tests in `tests/golden` pin it down.
+    #[cfg(feature = "stable-output")]
+    pub fn render_stable(&self) -> String {
+        let mut rendered = String::new();
+        for line in self.to_string().lines() {
+            rendered.push_str(line.trim_end_matches(' '));
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Renders the grid into the given buffer, replacing whatever was in
+    /// it, so a UI that redraws the grid every frame can keep using the same
+    /// buffer instead of allocating a new string each time.
+    pub fn render_into(&self, buf: &mut String) {
+        buf.clear();
+        self.write_to(buf, &[], 0 .. self.dimensions.num_lines, None).expect("writing to a String cannot fail");
+    }
+
+    /// Renders a horizontal slice of the grid, for scrolling a grid too wide
+    /// for the screen from side to side. The first `frozen_cols` columns are
+    /// always shown, such as a column of names, followed by the columns from
+    /// `first_col` to `last_col` inclusive, leaving out any of them that are
+    /// frozen already or past the last column.
+    ///
+    /// Each row is laid out as if the columns that are shown were the only
+    /// ones, with the same widths they have in the whole grid.
+    pub fn render_window(&self, first_col: usize, last_col: usize, frozen_cols: usize) -> String {
+        let num_columns = self.dimensions.widths.len();
+        let frozen_cols = min(frozen_cols, num_columns);
+        let window = max(first_col, frozen_cols) .. min(last_col.saturating_add(1), num_columns);
+        let columns: Vec<usize> = (0 .. frozen_cols).chain(window).collect();
+
+        let mut rendered = String::new();
+        self.write_to(&mut rendered, &[], 0 .. self.dimensions.num_lines, Some(&columns)).expect("writing to a String cannot fail");
+        rendered
+    }
+
+    /// Renders the grid a few rows at a time, passing each chunk of
+    /// `chunk_rows` rows to the given function as soon as it’s ready, so a
+    /// huge grid can be shown without waiting for all of it. Returning
+    /// `ControlFlow::Break` from the function stops rendering, and is passed
+    /// back to the caller; otherwise this returns `ControlFlow::Continue`
+    /// once every chunk has been passed on.
+    ///
+    /// Headers go in the chunk with the row they come before, and the
+    /// chunks put together are the same as the whole grid rendered at once.
+    pub fn render_chunks<F>(&self, chunk_rows: usize, mut chunk: F) -> ControlFlow<()>
+    where F: FnMut(&str) -> ControlFlow<()>
+    {
+        let num_lines = self.dimensions.num_lines;
+        let chunk_rows = chunk_rows.max(1);
+        let mut rendered = String::new();
+        let mut start = 0;
+
+        loop {
+            let end = min(start + chunk_rows, num_lines);
+            rendered.clear();
+            self.write_to(&mut rendered, &[], start .. end, None).expect("writing to a String cannot fail");
+            chunk(&rendered)?;
+
+            if end == num_lines {
+                return ControlFlow::Continue(());
+            }
+
+            start = end;
+        }
+    }
+
+    /// Writes the given range of rows, along with the headers that go
+    /// before them, cutting down any lines longer than the byte budget.
+    /// Only the given columns are written, if there are any, in the order
+    /// they’re given.
+    fn write_to(&self, f: &mut dyn fmt::Write, spans: &[Span<'_>], rows: Range<usize>, columns: Option<&[usize]>) -> fmt::Result {
+        match self.options.max_line_bytes {
+            Some(budget) => {
+                let mut f = ByteBudget { f, budget, line: String::new(), styled: Vec::new() };
+                self.write_rows(&mut f, spans, rows, columns)?;
+                f.finish()
+            },
+            None => self.write_rows(&mut Unbudgeted(f), spans, rows, columns),
+        }
+    }
+
+    /// Writes the given range of rows, along with the headers that go
+    /// before them. The column headers go with the first row, and the
+    /// headers of empty groups at the end go with the last.
+    fn write_rows(&self, f: &mut dyn StyledWrite, spans: &[Span<'_>], rows: Range<usize>, columns: Option<&[usize]>) -> fmt::Result {
+        let num_columns = self.dimensions.widths.len();
+        let all_columns: Vec<usize>;
+        let columns = match columns {
+            Some(columns)  => columns,
+            None           => { all_columns = (0 .. num_columns).collect(); &all_columns },
+        };
+        let margin = spaces(self.margin());
+        let header_margin = format!("{}{}", self.options.prefix(None), margin);
+        let gaps = self.justified_gaps(self.natural_width());
+        let start = rows.start;
+        let mut headers = self.headers().into_iter().skip_while(|&(row, _)| row < start).peekable();
+        let index_width = self.options.index_width(self.dimensions.num_lines);
+        let is_last = rows.end == self.dimensions.num_lines;
+        let plain = self.options.output_mode == OutputMode::Plain;
+
+        if start == 0 {
+            if let Some(column_headers) = self.column_headers() {
+                if plain {
+                    for &x in columns {
+                        writeln!(f, "Column {}: {}", x + 1, column_headers[x].contents)?;
+                    }
+                }
+                else {
+                    let cells: Vec<Option<&Cell>> = columns.iter().map(|&x| Some(&column_headers[x])).collect();
+                    self.write_row(f, &cells, &vec![ None; columns.len() ], columns, None, &margin, index_width, &gaps)?;
+                }
+            }
+        }
+
+        for y in rows {
+            while let Some((_, header)) = headers.next_if(|&(row, _)| row == y) {
+                self.write_header(f, header, &header_margin)?;
+            }
+
+            if plain {
+                for &x in columns {
+                    if let Some(index) = self.cell_index(y, x) {
+                        writeln!(f, "Row {}, column {}: {}", y + 1, x + 1, self.grid.cells[index].contents)?;
+                    }
+                }
+                continue;
+            }
+
+            let indices: Vec<Option<usize>> = columns.iter().map(|&x| self.cell_index(y, x)).collect();
+            let cells: Vec<Option<&Cell>> = indices.iter()
+                .map(|index| match index {
+                    Some(num) => Some(&self.grid.cells[*num]),
+                    None      => self.options.placeholder.as_ref(),
+                })
+                .collect();
+
+            let styles: Vec<Option<&Span<'_>>> = indices.iter()
+                .map(|index| index.and_then(|index| spans.iter().rev().find(|span| span.cells.contains(&index))))
+                .collect();
+
+            self.write_row(f, &cells, &styles, columns, Some(y), &margin, index_width, &gaps)?;
+        }
+
+        // Groups at the end with no cells still get their headers.
+        if is_last {
+            for (_, header) in headers {
+                self.write_header(f, header, &header_margin)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes one row of cells, along with any span styling them, over as
+    /// many lines as it takes. The cells and their styles go in the given
+    /// columns, one after the other. Rows of the grid, rather than of its
+    /// column headers, come with their index, for their label and prefix.
+    /// The gaps are the spaces added around each separator by `justify`.
+    #[allow(clippy::too_many_arguments)]
+    fn write_row(&self, f: &mut dyn StyledWrite, cells: &[Option<&Cell>], styles: &[Option<&Span<'_>>],
+                 columns: &[usize], row: Option<usize>, margin: &str, index_width: Width, gaps: &[Width]) -> fmt::Result {
+        let label = row.and_then(|row| self.options.index_column.as_ref().map(|index_column| index_column.label(row)));
+        let num_columns = self.dimensions.widths.len();
+        let num_shown = columns.len();
+        let pad_lines = self.options.pad_lines;
+        let continuous_rules = self.options.continuous_rules;
+        let annotation = row.and_then(|row| self.options.annotations.get(row)).filter(|annotation| !annotation.contents.is_empty());
+
+        let lines: Vec<Vec<(Cow<'_, str>, Width)>> = cells.iter().zip(columns)
+            .map(|(cell, &x)| cell.map_or_else(Vec::new, |cell| self.cell_lines(cell, x)))
+            .collect();
+
+        let height = lines.iter().map(Vec::len).max().unwrap_or(0).max(1);
+
+        for line in 0 .. height {
+
+            // Lines after the first only exist because a cell wrapped, so
+            // they stop after the last column with anything left to show,
+            // unless the rules between the columns have to carry on.
+            let end = if line == 0 || pad_lines {
+                num_shown
+            }
+            else if continuous_rules {
+                cells.iter().rposition(Option::is_some).map_or(0, |x| x + 1)
+            }
+            else {
+                (0 .. num_shown).rposition(|i| line_of(cells, &lines, i, line, height).is_some())
+                                  .map_or(0, |x| x + 1)
+            };
+
+            if end > 0 {
+                write!(f, "{}{}", self.options.prefix(row.filter(|_| line == 0)), margin)?;
+            }
+
+            // Lines with an annotation at the end are filled in all the way,
+            // so it can be put in the right place.
+            let annotation = annotation.filter(|_| line == 0 && end > 0);
+            let pad_line = pad_lines || annotation.is_some();
+
+            // Rows are labelled on their first line, like the separators.
+            match &label {
+                Some(label) if end > 0 && (line == 0 || continuous_rules) => {
+                    let label = if line == 0 { &label[..] } else { "" };
+                    let label_width = index_width - self.options.filling.width();
+                    let padding = label_width.saturating_sub(UnicodeWidthStr::width(label));
+                    let separator = match &self.options.filling {
+                        Filling::Text(text)  => text.clone(),
+                        filling              => spaces(filling.width()),
+                    };
+                    write!(f, "{}{}", pad_string(label, padding, Alignment::Right, ' '), separator)?;
+                },
+                _ if end > 0  => write!(f, "{}", spaces(index_width))?,
+                _             => {},
+            }
+
+            for (i, &x) in columns[.. end].iter().enumerate() {
+
+                // Abandon a line mid-way through if that’s where the cells
+                // end, unless the rest of it has to be filled in.
+                let Some(cell) = cells[i] else {
+                    if pad_line {
+                        let separator_width = if i == end - 1 { 0 } else { self.options.separator(x, num_columns).width() + gaps[x] };
+                        write!(f, "{}", spaces(self.dimensions.widths[x] + separator_width))?;
+                    }
+                    continue;
+                };
+
+                let (contents, width) = match line_of(cells, &lines, i, line, height) {
+                    Some((contents, width))  => (&contents[..], *width),
+                    None                     => ("", 0),
+                };
+
+                let span = styles[i];
+                let is_last = i == end - 1;
+
+                // A layout that doesn’t match the cells, such as one made for
+                // a different grid, can leave a cell wider than its column.
+                // It gets written out unpadded, pushing the rest of the line
+                // along, as a misaligned grid is better than no grid at all.
+                let extra_spaces = self.dimensions.widths[x].saturating_sub(width);
+
+                // The final column doesn’t need to have trailing spaces, and
+                // padding that goes after the separator is held back until
+                // the separator has been written.
+                let (padded, held_back) = if is_last && !pad_line && span.is_none() {
+                    (pad_string_trimmed(contents, extra_spaces, cell.alignment, cell.pad_char), 0)
+                }
+                else if !is_last && self.options.padding_placement == PaddingPlacement::AfterSeparator {
+                    let (before, after) = split_padding(extra_spaces, cell.alignment);
+                    (pad_string(contents, before, Alignment::Right, cell.pad_char), after)
+                }
+                else {
+                    (pad_string(contents, extra_spaces, cell.alignment, cell.pad_char), 0)
+                };
+
+                match span {
+                    Some(span)  => f.write_styled(span.before, &padded, span.after)?,
+                    None        => write!(f, "{}", padded)?,
+                }
+
+                if !is_last {
+                    let gap = gaps[x];
+                    write!(f, "{}", spaces(gap / 2))?;
+
+                    match &*self.options.separator(x, num_columns) {
+                        // Separators are only drawn on the first line of a row,
+                        // unless they’re meant to join up.
+                        Filling::Text(text) if line == 0 || continuous_rules  => write!(f, "{}", text)?,
+                        filling                                               => write!(f, "{}", spaces(filling.width()))?,
+                    }
+
+                    write!(f, "{}", spaces(gap - gap / 2))?;
+                    write!(f, "{}", repeat_n(cell.pad_char, held_back).collect::<String>())?;
+                }
+            }
+
+            // Annotations go as far to the right as they can, but always
+            // with the filling’s width of room before them.
+            if let Some(annotation) = annotation {
+                let row_width = self.options.prefix_width() + margin.len() + index_width
+                    + columns.iter().map(|&x| self.dimensions.widths[x]).sum::<Width>()
+                    + columns.iter().rev().skip(1).map(|&x| self.options.separator(x, num_columns).width() + gaps[x]).sum::<Width>();
+                let start = self.maximum_width.map_or(0, |width| width.saturating_sub(annotation.width));
+                let padding = max(start.saturating_sub(row_width), self.options.filling.width());
+                write!(f, "{}{}", spaces(padding), annotation.contents)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Styling for a range of cells, used with `Display::render_with_spans`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Span<'a> {
+
+    /// The indices of the cells to style, in the order they were added.
+    pub cells: Range<usize>,
+
+    /// Written before each cell, such as an escape code to turn a style on.
+    pub before: &'a str,
+
+    /// Written after each cell, such as an escape code to reset the style.
+    pub after: &'a str,
+}
+
+/// Picks which of the lines of the cell in column `x` to show on the given
+/// line of a row, taking the cell’s vertical alignment into account, or
+/// returns `None` if it has nothing to show there.
+fn line_of<'a, T>(cells: &[Option<&Cell>], lines: &'a [Vec<T>], x: usize, line: usize, height: usize) -> Option<&'a T> {
+    let cell_lines = &lines[x];
+    let offset = match cells[x].map(|cell| cell.vertical_alignment) {
+        Some(VerticalAlignment::Top) | None  => 0,
+        Some(VerticalAlignment::Middle)      => (height - cell_lines.len()) / 2,
+        Some(VerticalAlignment::Bottom)      => height - cell_lines.len(),
+    };
+
+    line.checked_sub(offset).and_then(|line| cell_lines.get(line))
+}
+
+/// Whether a string looks like a number: digits, perhaps grouped with commas
+/// or underscores, with an optional sign and decimal part, followed by an
+/// optional unit prefix such as `K` or `Mi`, an optional `B`, or a `%`.
+fn looks_like_number(string: &str) -> bool {
+    let string = string.trim();
+    let string = string.strip_prefix(['-', '+']).unwrap_or(string);
+
+    let number_end = string.find(|c: char| !(c.is_ascii_digit() || matches!(c, ',' | '_' | '.'))).unwrap_or(string.len());
+    let (number, unit) = string.split_at(number_end);
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let grouped_digits = |digits: &str| digits.split([',', '_']).all(|group| !group.is_empty() && group.bytes().all(|b| b.is_ascii_digit()));
+    if !grouped_digits(whole) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+
+    let unit = unit.strip_prefix(' ').unwrap_or(unit);
+    let unit = unit.strip_prefix(['k', 'K', 'M', 'G', 'T', 'P', 'E']).map_or(unit, |rest| rest.strip_prefix('i').unwrap_or(rest));
+    matches!(unit, "" | "B" | "b" | "%")
+}
+
+/// Pad a string with the given number of spaces.
+fn spaces(length: usize) -> String {
+    " ".repeat(length)
+}
+
+
+fn pad_string(string: &str, padding: usize, alignment: Alignment, pad_char: char) -> String {
+    let (before, after) = split_padding(padding, alignment);
+
+    let mut padded = String::with_capacity(string.len() + padding * pad_char.len_utf8());
+    padded.extend(repeat_n(pad_char, before));
+    padded.push_str(string);
+    padded.extend(repeat_n(pad_char, after));
+    padded
+}
+
+/// Split padding into the amounts that go before and after a string with
+/// the given alignment.
+fn split_padding(padding: usize, alignment: Alignment) -> (usize, usize) {
+    match alignment {
+        Alignment::Left    => (0, padding),
+        Alignment::Right   => (padding, 0),
+        Alignment::Center  => (padding / 2, padding - padding / 2),
+    }
+}
+
+/// Pad a string as `pad_string` does, but without any trailing spaces, for
+/// strings at the end of a line. Padding with other characters is kept, as
+/// it’s there to be seen.
+fn pad_string_trimmed(string: &str, padding: usize, alignment: Alignment, pad_char: char) -> String {
+    match alignment {
+        _ if pad_char != ' '  => pad_string(string, padding, alignment, pad_char),
+        Alignment::Left       => string.into(),
+        Alignment::Right      => pad_string(string, padding, Alignment::Right, pad_char),
+        Alignment::Center     => pad_string(string, padding / 2, Alignment::Right, pad_char),
+    }
+}
+
+/// Somewhere a display gets written to, which gets told where the codes
+/// that a span puts around a cell are, so they never get cut in half.
+trait StyledWrite: fmt::Write {
+
+    /// Writes a cell along with the codes written before and after it.
+    fn write_styled(&mut self, before: &str, contents: &str, after: &str) -> fmt::Result;
+}
+
+/// Passes everything on to another writer as it is.
+struct Unbudgeted<'f>(&'f mut dyn fmt::Write);
+
+impl fmt::Write for Unbudgeted<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s)
+    }
+}
+
+impl StyledWrite for Unbudgeted<'_> {
+    fn write_styled(&mut self, before: &str, contents: &str, after: &str) -> fmt::Result {
+        write!(self.0, "{}{}{}", before, contents, after)
+    }
+}
+
+/// Passes lines on to another writer, cutting down any that are longer than
+/// a number of bytes. Lines are held on to until they’re complete, so the
+/// end of one that’s too long can be swapped for an ellipsis.
+struct ByteBudget<'f> {
+    f: &'f mut dyn fmt::Write,
+    budget: usize,
+    line: String,
+
+    /// Where the codes before and after each styled cell are in the line.
+    styled: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl ByteBudget<'_> {
+
+    /// Writes out the line so far, cut down to the budget. The cut never
+    /// falls inside a span’s codes: a cell whose opening code doesn’t fit
+    /// is left out, and a cell that gets cut short still gets its closing
+    /// code, so the style doesn’t leak into the rest of the output.
+    fn flush(&mut self) -> fmt::Result {
+        if self.line.len() > self.budget {
+            let ellipsis = if self.budget >= '…'.len_utf8() { "…" } else { "" };
+            let mut end = self.budget - ellipsis.len();
+            let closing = loop {
+                while !self.line.is_char_boundary(end) {
+                    end -= 1;
+                }
+
+                let mut closing = None;
+                for (before, after) in &self.styled {
+                    if before.start < end && end < before.end {
+                        end = before.start;
+                    }
+                    else if before.end <= end && end < after.end {
+                        end = min(end, after.start);
+                        closing = Some(after.clone());
+                    }
+                }
+
+                let closing_len = closing.as_ref().map_or(0, Range::len);
+                if end == 0 || end + ellipsis.len() + closing_len <= self.budget {
+                    break closing;
+                }
+                end -= 1;
+            };
+
+            let closing = closing.map(|after| self.line[after].to_string());
+            self.line.truncate(end);
+            self.line.push_str(ellipsis);
+            self.line.push_str(closing.as_deref().unwrap_or(""));
+        }
+
+        self.f.write_str(&self.line)?;
+        self.line.clear();
+        self.styled.clear();
+        Ok(())
+    }
+
+    /// Writes out anything left over after the last line break.
+    fn finish(mut self) -> fmt::Result {
+        self.flush()
+    }
+}
+
+impl fmt::Write for ByteBudget<'_> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        while let Some(newline) = s.find('\n') {
+            self.line.push_str(&s[.. newline]);
+            self.flush()?;
+            self.f.write_char('\n')?;
+            s = &s[newline + 1 ..];
+        }
+
+        self.line.push_str(s);
+        Ok(())
+    }
+}
+
+impl StyledWrite for ByteBudget<'_> {
+    fn write_styled(&mut self, before: &str, contents: &str, after: &str) -> fmt::Result {
+        let start = self.line.len();
+        self.line.push_str(before);
+        let before = start .. self.line.len();
+        self.line.push_str(contents);
+
+        let start = self.line.len();
+        self.line.push_str(after);
+        self.styled.push((before, start .. self.line.len()));
+        Ok(())
+    }
+}
+
+/// Cut a string down to fit in the given width, removing characters from
+/// the given side and putting an ellipsis in their place if anything had to
+/// be removed. Returns the shortened string along with its width.
+fn truncate_string(string: &str, width: Width, side: TruncateSide, policy: EmojiWidth) -> (String, Width) {
+    let string_width = policy.width(string);
+    if string_width <= width {
+        return (string.into(), string_width);
+    }
+
+    if width == 0 {
+        return (String::new(), 0);
+    }
+
+    let room = width - 1;
+    let (head_room, tail_room) = match side {
+        TruncateSide::Left    => (0, room),
+        TruncateSide::Middle  => (room - room / 2, room / 2),
+        TruncateSide::Right   => (room, 0),
+    };
+
+    let clusters: Vec<_> = clusters(string, policy).collect();
+    let (head, head_width) = take_width(clusters.iter().copied(), head_room);
+    let (tail, tail_width) = take_width(clusters.iter().rev().copied(), tail_room);
+
+    let mut result = head.concat();
+    result.push('…');
+    result.extend(tail.into_iter().rev());
+    (result, head_width + 1 + tail_width)
+}
+
+/// The width of the widest piece of a string that can’t be broken apart,
+/// which is as narrow as it can be wrapped.
+fn widest_cluster(string: &str, policy: EmojiWidth) -> Width {
+    clusters(string, policy).map(|(_, width)| width).max().unwrap_or(0)
+}
+
+/// Collects pieces of a string until the next one would take it past the
+/// given width, returning them along with their width.
+fn take_width<'a>(clusters: impl Iterator<Item = (&'a str, Width)>, width: Width) -> (Vec<&'a str>, Width) {
+    let mut result = Vec::new();
+    let mut result_width = 0;
+    for (cluster, cluster_width) in clusters {
+        if result_width + cluster_width > width {
+            break;
+        }
+
+        result.push(cluster);
+        result_width += cluster_width;
+    }
+
+    (result, result_width)
+}
+
+/// Break a string into lines no wider than the given width, preferring to
+/// break between words. Words that are too long for a line on their own are
+/// split wherever they run out of room.
+fn wrap_string(string: &str, width: Width, policy: EmojiWidth) -> Vec<(String, Width)> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+
+    for word in string.split_whitespace() {
+        let word_width = policy.width(word);
+
+        if line_width > 0 && line_width + 1 + word_width <= width {
+            line.push(' ');
+            line.push_str(word);
+            line_width += 1 + word_width;
+            continue;
+        }
+
+        if line_width > 0 {
+            lines.push((line, line_width));
+            line = String::new();
+            line_width = 0;
+        }
+
+        for (cluster, cluster_width) in clusters(word, policy) {
+            if line_width > 0 && line_width + cluster_width > width {
+                lines.push((line, line_width));
+                line = String::new();
+                line_width = 0;
+            }
+
+            line.push_str(cluster);
+            line_width += cluster_width;
+        }
+    }
+
+    if line_width > 0 || lines.is_empty() {
+        lines.push((line, line_width));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn no_items() {
+        let grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+
+        let display = grid.fit_into_width(40).unwrap();
+
+        assert_eq!(display.dimensions.num_lines, 0);
+        assert!(display.dimensions.widths.is_empty());
+
+        assert_eq!(display.width(), 0);
+    }
+
+    #[test]
+    fn one_item() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+
+        grid.add(Cell::from("1"));
+
+        let display = grid.fit_into_width(40).unwrap();
+
+        assert_eq!(display.dimensions.num_lines, 1);
+        assert_eq!(display.dimensions.widths, vec![ 1 ]);
+
+        assert_eq!(display.width(), 1);
+    }
+
+    #[test]
+    fn one_item_exact_width() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+
+        grid.add(Cell::from("1234567890"));
+
+        let display = grid.fit_into_width(10).unwrap();
+
+        assert_eq!(display.dimensions.num_lines, 1);
+        assert_eq!(display.dimensions.widths, vec![ 10 ]);
+
+        assert_eq!(display.width(), 10);
+    }
+
+    #[test]
+    fn one_item_just_over() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+
+        grid.add(Cell::from("1234567890!"));
+
+        assert_eq!(grid.fit_into_width(10), None);
+    }
+
+    #[test]
+    fn two_small_items() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+
+        grid.add(Cell::from("1"));
+        grid.add(Cell::from("2"));
+
+        let display = grid.fit_into_width(40).unwrap();
+
+        assert_eq!(display.dimensions.num_lines, 1);
+        assert_eq!(display.dimensions.widths, vec![ 1, 1 ]);
+
+        assert_eq!(display.width(), 1 + 2 + 1);
+    }
+
+    #[test]
+    fn two_medium_size_items() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+
+        grid.add(Cell::from("hello there"));
+        grid.add(Cell::from("how are you today?"));
+
+        let display = grid.fit_into_width(40).unwrap();
+
+        assert_eq!(display.dimensions.num_lines, 1);
+        assert_eq!(display.dimensions.widths, vec![ 11, 18 ]);
+
+        assert_eq!(display.width(), 11 + 2 + 18);
+    }
+
+    #[test]
+    fn two_big_items() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+
+        grid.add(Cell::from("nuihuneihsoenhisenouiuteinhdauisdonhuisudoiosadiuohnteihaosdinhteuieudi"));
+        grid.add(Cell::from("oudisnuthasuouneohbueobaugceoduhbsauglcobeuhnaeouosbubaoecgueoubeohubeo"));
+
+        assert_eq!(grid.fit_into_width(40), None);
+    }
+
+    #[test]
+    fn that_example_from_earlier() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+
+        for s in &["one", "two", "three", "four", "five", "six", "seven",
+                   "eight", "nine", "ten", "eleven", "twelve"]
+        {
+            grid.add(Cell::from(*s));
+        }
+
+        let bits = "one  two three  four\nfive six seven  eight\nnine ten eleven twelve\n";
+        assert_eq!(grid.fit_into_width(24).unwrap().to_string(), bits);
+        assert_eq!(grid.fit_into_width(24).unwrap().row_count(), 3);
+    }
+
+    #[test]
+    fn number_grid_with_pipe() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight));
+
+        for s in &["one", "two", "three", "four", "five", "six", "seven",
+                   "eight", "nine", "ten", "eleven", "twelve"]
+        {
+            grid.add(Cell::from(*s));
+        }
+
+        let bits = "one |two|three |four\nfive|six|seven |eight\nnine|ten|eleven|twelve\n";
+        assert_eq!(grid.fit_into_width(24).unwrap().to_string(), bits);
+        assert_eq!(grid.fit_into_width(24).unwrap().row_count(), 3);
+    }
+
+    #[test]
+    fn numbers_right() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+
+        for s in &["one", "two", "three", "four", "five", "six", "seven",
+                   "eight", "nine", "ten", "eleven", "twelve"]
+        {
+            let mut cell = Cell::from(*s);
+            cell.alignment = Alignment::Right;
+            grid.add(cell);
+        }
+
+        let bits = " one two  three   four\nfive six  seven  eight\nnine ten eleven twelve\n";
+        assert_eq!(grid.fit_into_width(24).unwrap().to_string(), bits);
+        assert_eq!(grid.fit_into_width(24).unwrap().row_count(), 3);
+    }
+
+    #[test]
+    fn rows_left_to_right() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+
+        for s in &["one", "two", "three", "four", "five", "six", "seven",
+                   "eight", "nine", "ten", "eleven", "twelve", "thirteen"]
+        {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(33).unwrap();
+        let rows: Vec<(usize, Vec<&str>)> = display.rows()
+            .map(|(y, cells)| (y, cells.iter().map(|c| &*c.contents).collect()))
+            .collect();
+
+        assert_eq!(rows, vec![ (0, vec![ "one", "two", "three", "four", "five" ]),
+                               (1, vec![ "six", "seven", "eight", "nine", "ten" ]),
+                               (2, vec![ "eleven", "twelve", "thirteen" ]) ]);
+    }
+
+    #[test]
+    fn rows_top_to_bottom() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+
+        for s in &["one", "two", "three", "four", "five", "six", "seven",
+                   "eight", "nine", "ten", "eleven", "twelve", "thirteen"]
+        {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(33).unwrap();
+        let rows: Vec<Vec<&str>> = (&display).into_iter()
+            .map(|(_, cells)| cells.iter().map(|c| &*c.contents).collect())
+            .collect();
+
+        assert_eq!(rows, vec![ vec![ "one", "four", "seven", "ten", "thirteen" ],
+                               vec![ "two", "five", "eight", "eleven" ],
+                               vec![ "three", "six", "nine", "twelve" ] ]);
+    }
+
+    #[test]
+    fn placeholder_fills_last_row() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+            .placeholder("-"));
+
+        for s in &["one", "two", "three", "four", "five", "six", "seven",
+                   "eight", "nine", "ten", "eleven", "twelve", "thirteen"]
+        {
+            grid.add(Cell::from(*s));
+        }
+
+        let bits = "one    two    three    four five\n\
+                    six    seven  eight    nine ten\n\
+                    eleven twelve thirteen -    -\n";
+        assert_eq!(grid.fit_into_width(33).unwrap().to_string(), bits);
+    }
+
+    #[test]
+    fn placeholder_widens_column() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+            .placeholder("(none)"));
+
+        for s in &["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let dimensions = grid.fitter().column_widths(2, 7);
+        assert_eq!(dimensions.widths, vec![ 1, 1, 1, 1, 1, 1, 6 ]);
+    }
+
+    #[test]
+    fn truncate_with_ellipsis() {
+        assert_eq!(truncate_string("hello", 5, TruncateSide::Right, EmojiWidth::Measured), ("hello".into(), 5));
+        assert_eq!(truncate_string("hello world", 5, TruncateSide::Right, EmojiWidth::Measured), ("hell…".into(), 5));
+        assert_eq!(truncate_string("日本語", 4, TruncateSide::Right, EmojiWidth::Measured), ("日…".into(), 3));
+        assert_eq!(truncate_string("hello", 0, TruncateSide::Right, EmojiWidth::Measured), ("".into(), 0));
+    }
+
+    #[test]
+    fn truncate_other_sides() {
+        assert_eq!(truncate_string("/home/user/file.txt", 10, TruncateSide::Left, EmojiWidth::Measured), ("…/file.txt".into(), 10));
+        assert_eq!(truncate_string("/home/user/file.txt", 12, TruncateSide::Middle, EmojiWidth::Measured), ("/home/…e.txt".into(), 12));
+        assert_eq!(truncate_string("日本語です", 6, TruncateSide::Middle, EmojiWidth::Measured), ("日…す".into(), 5));
+        assert_eq!(truncate_string("hello", 1, TruncateSide::Left, EmojiWidth::Measured), ("…".into(), 1));
+    }
+
+    #[test]
+    fn wrap_between_words() {
+        assert_eq!(wrap_string("the quick brown fox", 10, EmojiWidth::Measured),
+                   vec![ ("the quick".into(), 9), ("brown fox".into(), 9) ]);
+        assert_eq!(wrap_string("abcdefghij klm", 4, EmojiWidth::Measured),
+                   vec![ ("abcd".into(), 4), ("efgh".into(), 4), ("ij".into(), 2), ("klm".into(), 3) ]);
+        assert_eq!(wrap_string("", 4, EmojiWidth::Measured), vec![ ("".into(), 0) ]);
+    }
+
+    #[test]
+    fn wide_emoji_sequences() {
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let heart = "❤\u{FE0F}";
+
+        assert_eq!(EmojiWidth::Wide.width(family), 2);
+        assert_eq!(EmojiWidth::Wide.width(heart), 2);
+        assert_eq!(EmojiWidth::Wide.width("👍\u{1F3FD}ok"), 4);
+        assert_eq!(EmojiWidth::Wide.width("a\u{FE0E}"), 1);
+
+        let long = format!("{}{}{}", family, heart, family);
+        assert_eq!(truncate_string(&long, 5, TruncateSide::Right, EmojiWidth::Wide), (format!("{}{}…", family, heart), 5));
+        assert_eq!(wrap_string(&long, 4, EmojiWidth::Wide),
+                   vec![ (format!("{}{}", family, heart), 4), (family.into(), 2) ]);
+
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+            .emoji_width(EmojiWidth::Wide));
+        for s in &[family, "ab", heart, "c"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(10).unwrap();
+        assert_eq!(display.width(), 10);
+        assert_eq!(display.to_string(), format!("{} ab {} c\n", family, heart));
+    }
+
+    #[test]
+    fn wide_emoji_everywhere() {
+        // A skin tone modifier on a letter joins up with it when sequences
+        // are taken to be two columns wide, but not in the Unicode tables.
+        let toned = "a\u{1F3FD}";
+        assert_eq!(EmojiWidth::Measured.width(toned), 3);
+
+        let grid = |emoji_width| {
+            let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                .placeholder(toned)
+                .annotations([ toned ])
+                .line_prefix(LinePrefix::Text(toned.into()))
+                .column_headers(move |_| toned)
+                .emoji_width(emoji_width));
+            grid.start_group(toned.repeat(3));
+            for s in &["b", "c", "d"] {
+                grid.add(Cell::from(*s));
+            }
+            grid
+        };
+
+        assert_eq!(grid(EmojiWidth::Measured).fit_into_width(11), None);
+
+        let wide = grid(EmojiWidth::Wide);
+        let display = wide.fit_into_width(8).unwrap();
+        assert_eq!(display.width(), 8);
+        assert_eq!(display.to_string(), format!("{t}{t}\n{t}{t}{t}{t}\n{t}b   {t}\n{t}c\n{t}d\n", t = toned));
+    }
+
+    #[test]
+    fn max_column_width_truncates() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight)
+            .max_column_width(8));
+
+        for s in &["README.md", "a_very_long_file_name.txt", "src"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(40).unwrap();
+        assert_eq!(display.dimensions.widths, vec![ 8, 8, 3 ]);
+        assert_eq!(display.to_string(), "README.…  a_very_…  src\n");
+    }
+
+    #[test]
+    fn max_column_width_wraps() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight)
+            .max_column_width(8)
+            .overflow(Overflow::Wrap));
+
+        for s in &["one", "two three four", "five"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(40).unwrap();
+        assert_eq!(display.dimensions.widths, vec![ 3, 8, 4 ]);
+        assert_eq!(display.to_string(), "one|two     |five\n    three\n    four\n");
+    }
+
+    #[test]
+    fn wrapped_column_as_wide_as_its_widest_character() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+            .max_column_width(1)
+            .overflow(Overflow::Wrap));
+
+        for s in &["一", "二", "三"] {
+            grid.add(Cell::from(*s));
+        }
+
+        // Each character is two columns wide, and can’t be wrapped any
+        // narrower than that, whatever the maximum column width.
+        assert_eq!(grid.fit_into_width(5), None);
+
+        let display = grid.fit_into_width(9).unwrap();
+        assert_eq!(display.dimensions.widths, vec![ 2, 2, 2 ]);
+        assert_eq!(display.width(), 8);
+        assert_eq!(display.to_string(), "一 二 三\n");
+    }
+
+    #[test]
+    fn render_to_frame_clips() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for s in &["ab", "日本", "cd", "e"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(40).unwrap();
+        let mut frame = vec![ '.'; 8 * 2 ];
+        display.render_to_frame(&mut frame, 8, (1, 1));
+
+        let frame: String = frame.into_iter().collect();
+        assert_eq!(frame, "........\
+                           .ab 日\0本\0");
+    }
+
+    #[test]
+    fn cell_areas_cover_wrapped_rows() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight)
+            .max_column_width(8)
+            .overflow(Overflow::Wrap));
+
+        for s in &["one", "two three four", "five"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(40).unwrap();
+        assert_eq!(display.cell_areas(), vec![
+            CellArea { index: 0, x: 0,  y: 0, width: 3, height: 3 },
+            CellArea { index: 1, x: 4,  y: 0, width: 8, height: 3 },
+            CellArea { index: 2, x: 13, y: 0, width: 4, height: 3 },
+        ]);
+    }
+
+    #[test]
+    fn candidate_layouts() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for s in &["one", "two", "three", "four", "five"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let candidates = grid.candidate_layouts(16);
+        assert_eq!(candidates, vec![
+            Layout { num_lines: 2, widths: vec![ 4, 4, 5 ] },
+            Layout { num_lines: 3, widths: vec![ 5, 4 ] },
+            Layout { num_lines: 5, widths: vec![ 5 ] },
+        ]);
+    }
+
+    #[test]
+    fn score_narrowest() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+            .layout_score(LayoutScore::NarrowestWidth));
+        for s in &["one", "two", "three", "four", "five"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(15).unwrap();
+        assert_eq!(display.dimensions.widths, vec![ 5 ]);
+    }
+
+    #[test]
+    fn score_balanced() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+            .layout_score(LayoutScore::BalancedColumns));
+        for s in &["a", "b", "c", "d", "e", "f", "g", "h", "i"] {
+            grid.add(Cell::from(*s));
+        }
+
+        // Three columns of three, rather than four columns of two and a
+        // last column of one, which would take up fewer lines.
+        let display = grid.fit_into_width(9).unwrap();
+        assert_eq!(display.dimensions.num_lines, 3);
+        assert_eq!(display.to_string(), "a d g\nb e h\nc f i\n");
+    }
+
+    #[test]
+    fn score_custom() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+            .layout_score(LayoutScore::custom(|dimensions| dimensions.widths.len().abs_diff(2))));
+        for s in &["one", "two", "three", "four", "five"] {
+            grid.add(Cell::from(*s));
+        }
+
+        let display = grid.fit_into_width(15).unwrap();
+        assert_eq!(display.dimensions.widths, vec![ 5, 4 ]);
+    }
+
+    #[test]
+    fn current_fit_while_streaming() {
+        let words = ["one", "two", "three", "four", "five", "six", "seven",
+                     "eight", "nine", "ten", "eleven", "twelve", "thirteen"];
+
+        let mut streamed = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        let mut fresh = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+
+        for word in &words {
+            streamed.add(Cell::from(*word));
+            fresh.add(Cell::from(*word));
+
+            let streamed_rows = streamed.current_fit(21).map(|display| display.row_count());
+            if let Some(expected) = fresh.fit_into_width(21).map(|display| display.row_count()) {
+                assert!(streamed_rows.is_some_and(|rows| rows <= expected));
+            }
+        }
+
+        assert_eq!(streamed.current_fit(21).unwrap().to_string(),
+                   "one   six   eleven\ntwo   seven twelve\nthree eight thirteen\nfour  nine  \nfive  ten   \n");
+        assert_eq!(streamed.fit_hint, Some((21, 5)));
+        assert_eq!(streamed.fitter().widths_widest_first().collect::<Vec<_>>(), vec![ 8, 6, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 3 ]);
+    }
+
+    #[test]
+    fn tie_break() {
+        for (tie_break, widths) in [ (TieBreak::FirstFit, vec![ 1, 4, 1, 1 ]),
+                                     (TieBreak::WidestColumnLast, vec![ 1, 4 ]) ] {
+            let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                .layout_score(LayoutScore::custom(|_| 0))
+                .tie_break(tie_break));
+            for s in &["a", "dddd", "b", "c"] {
+                grid.add(Cell::from(*s));
+            }
+
+            assert_eq!(grid.fit_into_width(20).unwrap().dimensions.widths, widths);
+        }
+    }
+
+    #[test]
+    fn from_rows() {
+        let grid = Grid::from_rows(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom), vec![
+            vec![ "name", "size", "modified" ],
+            vec![ "Cargo.toml", "293" ],
+            vec![ "src", "-", "yesterday" ],
+        ]);
+
+        let display = grid.fit_into_width(80).unwrap();
+        assert_eq!(display.dimensions.num_lines, 3);
+        assert_eq!(display.dimensions.widths, vec![ 10, 4, 9 ]);
+        assert_eq!(display.to_string(), "\
+            name        size  modified\n\
+            Cargo.toml  293   \n\
+            src         -     yesterday\n");
+
+        assert_eq!(grid.fit_into_width(26), None);
+    }
+
+    #[test]
+    fn from_lines() {
+        let input = "name   size\n\nCargo.toml\t293\n  src  -  \n";
+        let grid = Grid::from_lines(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight), input.lines(), None);
+        assert_eq!(grid.fit_into_width(80).unwrap().to_string(), "\
+            name        size\n\
+            Cargo.toml  293\n\
+            src         -\n");
+
+        let grid = Grid::from_lines(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight), [ "a,,b", "ccc:d,e" ], Some(",:"));
+        assert_eq!(grid.fit_into_width(80).unwrap().to_string(), "\
+            a       b\n\
+            ccc  d  e\n");
+    }
+
+    #[test]
+    fn vertical_alignment() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight)
+            .max_column_width(5)
+            .overflow(Overflow::Wrap));
+
+        let mut middle = Cell::from("mid");
+        middle.vertical_alignment = VerticalAlignment::Middle;
+        let mut bottom = Cell::from("bot");
+        bottom.vertical_alignment = VerticalAlignment::Bottom;
+
+        grid.add(Cell::from("top"));
+        grid.add(Cell::from("a b c d e f g h"));
+        grid.add(middle);
+        grid.add(bottom);
+
+        let display = grid.fit_into_width(40).unwrap();
+        assert_eq!(display.to_string(), "top|a b c|   |\n    d e f mid\n    g h       bot\n");
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn nfc() {
+        let mut cell = Cell::from("cafe\u{301}.txt");
+        cell.alignment = Alignment::Right;
+
+        let cell = cell.nfc();
+        assert_eq!(cell.contents, "caf\u{e9}.txt");
+        assert_eq!(cell.width, 8);
+        assert_eq!(cell.alignment, Alignment::Right);
+    }
+
+    #[test]
+    fn sanitize() {
+        let cell = Cell::from("bad\rname\x07\x7F\u{9B}.txt").sanitize();
+        assert_eq!(cell.contents, "bad^Mname^G^?\u{FFFD}.txt");
+        assert_eq!(cell.width, 18);
+
+        assert_eq!(Cell::from("fine.txt").sanitize(), Cell::from("fine.txt"));
+    }
+
+    #[test]
+    fn replaced_contents() {
+        let mut cell = Cell::from("one");
+        cell.set_contents(String::from("日本語"));
+        assert_eq!(cell.contents(), "日本語");
+        assert_eq!(cell.width, 6);
+    }
+
+    #[test]
+    fn built_cells() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+                                     .max_column_width(4)
+                                     .overflow(Overflow::Wrap)
+                                     .pad_lines(true));
+        grid.add(Cell::from("one two"));
+        grid.add(Cell::from("3").alignment(Alignment::Right).vertical_alignment(VerticalAlignment::Bottom).pad_char('.'));
+
+        let display = grid.fit_into_width(80).unwrap();
+        assert_eq!(display.to_string(), "one  .\ntwo  3\n");
+    }
+
+    #[test]
+    fn reuse_layout() {
+        let mut first = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        let mut second = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for s in &["three", "a", "b", "seven", "c"] {
+            first.add(Cell::from(*s));
+            second.add(Cell::from(s.to_uppercase()));
+        }
+
+        let layout = first.fit_into_width(11).unwrap().layout().clone();
+        assert_eq!(layout, Layout { num_lines: 2, widths: vec![ 5, 1, 1 ] });
+
+        let display = Display::from_layout(&second, layout.clone()).unwrap();
+        assert_eq!(display.to_string(), "THREE A B\nSEVEN C \n");
+
+        assert_eq!(Display::from_layout(&second, Layout { num_lines: 2, widths: vec![ 4, 4 ] }), None);
+        assert_eq!(Display::from_layout(&second, Layout { num_lines: 0, widths: vec![] }), None);
+    }
+
+    #[test]
+    fn aligned_within_width() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .horizontal_alignment(GridAlign::Right));
+        for s in &["three", "a", "b", "seven", "c"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(20).unwrap();
+        assert_eq!(display.width(), 17);
+        assert_eq!(display.to_string(), "   three a b seven c\n");
+
+        let display = grid.fit_into_width(12).unwrap();
+        assert_eq!(display.width(), 9);
+        assert_eq!(display.to_string(), "   three a b\n   seven c \n");
+
+        let layout = display.layout().clone();
+        let display = Display::from_layout(&grid, layout).unwrap();
+        assert_eq!(display.to_string(), "three a b\nseven c \n");
+    }
+
+    #[test]
+    fn centered_within_width() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight)
+                                     .horizontal_alignment(GridAlign::Center));
+        grid.add("ab".into());
+        grid.add("cd".into());
+
+        assert_eq!(grid.fit_into_width(11).unwrap().to_string(), "  ab  cd\n");
+    }
+
+    #[test]
+    fn separator_overrides() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .separator_at(1, Filling::Text(" | ".into())));
+        for s in &["aaa", "b", "c", "ddd", "e", "f"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(10).unwrap();
+        assert_eq!(display.width(), 3 + 1 + 1 + 3 + 1);
+        assert_eq!(display.to_string(), "aaa b | c\nddd e | f\n");
+
+        // Six columns need room for the wider separator too.
+        let display = grid.fit_into_width(17).unwrap();
+        assert_eq!(display.row_count(), 1);
+        assert_eq!(display.width(), 10 + 1 + 3 + 1 + 1 + 1);
+    }
+
+    #[test]
+    fn padded_lines() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight)
+                                     .max_column_width(5)
+                                     .overflow(Overflow::Wrap)
+                                     .pad_lines(true));
+        for s in &["one", "t", "abc defgh", "x"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(8).unwrap();
+        assert_eq!(display.width(), 7);
+
+        let rendered = display.to_string();
+        assert!(rendered.lines().all(|line| UnicodeWidthStr::width(line) == display.width()));
+        assert_eq!(rendered, "one  |t\nabc  |x\ndefgh  \n");
+    }
+
+    #[test]
+    fn continuous_rules() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight)
+                                     .max_column_width(5)
+                                     .overflow(Overflow::Wrap)
+                                     .index_column(IndexColumn::OneBased)
+                                     .continuous_rules(true));
+        for s in &["one", "two", "abc defgh", "x", "y"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(12).unwrap();
+        assert_eq!(display.to_string(), "1|one  |two\n2|abc  |x\n |defgh|\n3|y    |\n");
+    }
+
+    #[test]
+    fn padded_lines_with_missing_cells() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight)
+                                     .pad_lines(true));
+        for s in &["one", "t", "three"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(9).unwrap();
+        assert_eq!(display.to_string(), "one    t\nthree   \n");
+    }
+
+    #[test]
+    fn fit_after_adding() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        grid.add("three".into());
+        grid.add("b".into());
+
+        let before = grid.fit_into_width(8).unwrap().layout().clone();
+        assert_eq!(before, Layout { num_lines: 1, widths: vec![ 5, 1 ] });
+
+        let after = grid.fit_after_adding(&"seven".into(), 8).unwrap();
+        assert_eq!(after, Layout { num_lines: 2, widths: vec![ 5, 1 ] });
+        assert_eq!(grid.fit_into_width(8).unwrap().layout(), &before);
+
+        assert!(grid.would_fit(&"ten".into(), 8));
+        assert!(!grid.would_fit(&"eleven characters".into(), 8));
+    }
+
+    #[test]
+    fn fit_after_adding_without_copying_the_grid() {
+        let plain = GridOptions::new(Filling::Spaces(1), Direction::TopToBottom);
+        let options = [ plain.clone(),
+                        plain.clone().layout_score(LayoutScore::BalancedColumns),
+                        plain.clone().max_column_width(4).overflow(Overflow::Wrap),
+                        plain.clone().emoji_width(EmojiWidth::Wide) ];
+
+        for options in options {
+            for (fixed_columns, grouped) in [ (None, false), (Some(2), false), (None, true), (Some(3), true) ] {
+                let mut grid = Grid::new(options.clone());
+                grid.fixed_columns = fixed_columns;
+                for (index, s) in ["one", "two", "three", "four", "five", "six"].iter().enumerate() {
+                    if grouped && index % 3 == 0 {
+                        grid.start_group(format!("{}:", index));
+                    }
+                    grid.add((*s).into());
+                }
+                grid.set_visible(1, false);
+
+                for cell in [ "a", "seven", "twelve\u{1F3FD}", "a much longer cell" ] {
+                    let mut added = grid.clone();
+                    added.add(cell.into());
+
+                    for width in 0 .. 30 {
+                        assert_eq!(grid.fit_after_adding(&cell.into(), width), added.width_dimensions(width, None),
+                                   "{:?} into {} with {:?} columns, grouped: {}", cell, width, fixed_columns, grouped);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn groups() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight));
+        grid.add("README".into());
+        grid.start_group("src:");
+        for s in &["lib.rs", "main.rs", "table.rs"] {
+            grid.add((*s).into());
+        }
+        grid.start_group("empty:");
+        grid.start_group("tests:");
+        for s in &["a.rs", "b.rs"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(30).unwrap();
+        assert_eq!(display.layout(), &Layout { num_lines: 3, widths: vec![ 6, 7, 8 ] });
+        assert_eq!(display.to_string(), "\
+            README  \n\
+            src:\n\
+            lib.rs  main.rs  table.rs\n\
+            empty:\n\
+            tests:\n\
+            a.rs    b.rs     \n");
+
+        let display = grid.fit_into_width(20).unwrap();
+        assert_eq!(display.layout(), &Layout { num_lines: 4, widths: vec![ 8, 7 ] });
+        assert_eq!(display.rows().map(|(_, cells)| cells.len()).collect::<Vec<_>>(), vec![ 1, 2, 1, 2 ]);
+    }
+
+    #[test]
+    fn groups_top_to_bottom() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        grid.start_group("one:");
+        for s in &["a", "b", "c"] {
+            grid.add((*s).into());
+        }
+        grid.start_group("two:");
+        grid.add("d".into());
+
+        let display = grid.fit_into_width(4).unwrap();
+        assert_eq!(display.to_string(), "one:\na c\nb \ntwo:\nd \n");
+        let stored = Display::from_layout(&grid, display.layout().clone()).unwrap();
+        assert_eq!(stored.to_string(), display.to_string());
+    }
+
+    #[test]
+    fn group_header_too_wide() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        grid.start_group("a very long header");
+        grid.add("a".into());
+
+        assert_eq!(grid.fit_into_width(10), None);
+        assert_eq!(grid.fit_into_width(18).unwrap().width(), 18);
+    }
+
+    #[test]
+    fn spans() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight));
+        for s in &["three", "a", "seven", "bb", "eight"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(9).unwrap();
+        let spans = [
+            Span { cells: 1 .. 3, before: "<", after: ">" },
+            Span { cells: 4 .. 5, before: "[", after: "]" },
+        ];
+
+        assert_eq!(display.render_with_spans(&[]), display.to_string());
+        assert_eq!(display.render_with_spans(&spans), "three|<a >\n<seven>|bb\n[eight]|\n");
+    }
+
+    #[test]
+    fn validation() {
+        let options = GridOptions::new(Filling::Spaces(1), Direction::LeftToRight);
+        assert_eq!(options.validate(), Ok(()));
+
+        let options = GridOptions::new(Filling::Spaces(0), Direction::LeftToRight);
+        assert_eq!(Grid::try_new(options).err(), Some(OptionsError::ZeroWidthSeparator { index: None }));
+
+        let options = GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                          .separator_at(2, Filling::Text(String::new()));
+        assert_eq!(options.validate(), Err(OptionsError::ZeroWidthSeparator { index: Some(2) }));
+
+        let options = GridOptions::new(Filling::Text(" |\n".into()), Direction::LeftToRight);
+        assert_eq!(options.validate(), Err(OptionsError::MultiLineSeparator { index: None }));
+
+        let options = GridOptions::new(Filling::Spaces(1), Direction::LeftToRight).max_column_width(0);
+        assert_eq!(options.validate().unwrap_err().to_string(), "the maximum column width is zero");
+    }
+
+    #[test]
+    fn estimated_columns() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight));
+        assert_eq!(grid.estimate_columns(80), 0);
+
+        for s in &["one", "two", "six", "ten", "red", "zip"] {
+            grid.add((*s).into());
+        }
+
+        assert_eq!(grid.estimate_columns(14), 3);
+        assert_eq!(grid.estimate_columns(14), grid.candidate_layouts(14)[0].widths.len());
+        assert_eq!(grid.estimate_columns(80), 6);
+        assert_eq!(grid.estimate_columns(2), 0);
+    }
+
+    #[test]
+    fn estimated_columns_with_extras() {
+        let mut indexed = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+                                        .index_column(IndexColumn::OneBased));
+        for s in &["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"] {
+            indexed.add((*s).into());
+        }
+
+        assert_eq!(indexed.estimate_columns(6), 2);
+        assert_eq!(indexed.estimate_columns(6), indexed.fit_into_width(6).unwrap().layout().widths.len());
+
+        let mut shrinking = Grid::new(GridOptions::new(Filling::Spaces(3), Direction::LeftToRight)
+                                          .min_separator_width(1));
+        for s in &["three", "a", "b", "seven", "c", "d"] {
+            shrinking.add((*s).into());
+        }
+
+        assert_eq!(shrinking.estimate_columns(12), 3);
+        assert_eq!(shrinking.estimate_columns(12), shrinking.fit_into_width(12).unwrap().layout().widths.len());
+    }
+
+    #[test]
+    fn index_column() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+                                     .index_column(IndexColumn::OneBased));
+        for s in &["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(6).unwrap();
+        assert_eq!(display.width(), 5);
+        assert_eq!(display.to_string(), "\
+             1 a f\n\
+             2 b g\n\
+             3 c h\n\
+             4 d i\n\
+             5 e j\n");
+
+        // Ten rows need a wider index column.
+        let display = grid.fit_into_width(5).unwrap();
+        assert_eq!(display.row_count(), 10);
+        assert!(display.to_string().starts_with(" 1 a\n 2 b\n"));
+        assert!(display.to_string().ends_with("10 j\n"));
+    }
+
+    #[test]
+    fn custom_index_column() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text(" | ".into()), Direction::LeftToRight)
+                                     .index_column(IndexColumn::custom(|row| format!("{}:", (b'a' + row as u8) as char))));
+        for s in &["one", "two", "three"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(17).unwrap();
+        assert_eq!(display.to_string(), "a: | one   | two\nb: | three | \n");
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        for s in &["one", "two", "t"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(6).unwrap();
+        assert_eq!(display.to_json(), serde_json::json!({
+            "rows": 2,
+            "column_widths": [ 3, 1 ],
+            "cells": [
+                { "row": 0, "col": 0, "contents": "one" },
+                { "row": 0, "col": 1, "contents": "t" },
+                { "row": 1, "col": 0, "contents": "two" },
+            ],
+        }));
+    }
+
+    #[test]
+    fn centered_and_padded_cells() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        for (contents, alignment, pad_char) in [ ("apples", Alignment::Left, '.'), ("figgy", Alignment::Left, '.'),
+                                                 ("7", Alignment::Right, '0'), ("123", Alignment::Right, '0'),
+                                                 ("ab", Alignment::Center, ' '), ("abcd", Alignment::Center, ' ') ] {
+            let mut cell = Cell::from(contents);
+            cell.alignment = alignment;
+            cell.pad_char = pad_char;
+            grid.add(cell);
+        }
+
+        let display = grid.fit_into_width(16).unwrap();
+        assert_eq!(display.to_string(), "apples 007  ab\nfiggy. 123 abcd\n");
+    }
+
+    #[test]
+    fn widest_cell() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        assert_eq!(grid.widest_cell(), None);
+
+        for s in &["", "one", "three", "seven", "two"] {
+            grid.add((*s).into());
+        }
+
+        assert_eq!(grid.widest_cell(), Some((2, &Cell::from("three"))));
+    }
+
+    #[test]
+    fn shrinking_separators() {
+        let options = GridOptions::new(Filling::Spaces(2), Direction::TopToBottom);
+        let mut grid = Grid::new(options.clone());
+        let mut shrinking = Grid::new(options.min_separator_width(1));
+        for s in &["three", "four", "be", "a"] {
+            grid.add((*s).into());
+            shrinking.add((*s).into());
+        }
+
+        assert_eq!(grid.fit_into_width(9).unwrap().row_count(), 3);
+
+        let display = shrinking.fit_into_width(9).unwrap();
+        assert_eq!(display.width(), 8);
+        assert_eq!(display.to_string(), "three be\nfour  a\n");
+
+        // Shrinking the separators wouldn’t save a line here.
+        assert_eq!(shrinking.fit_into_width(10).unwrap().to_string(), "three  be\nfour   a\n");
+    }
+
+    #[test]
+    fn shrinking_separators_with_groups_and_an_index() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(3), Direction::TopToBottom)
+                                     .index_column(IndexColumn::OneBased)
+                                     .min_separator_width(1));
+        grid.start_group("x:");
+        for s in &["one", "two", "three"] {
+            grid.add((*s).into());
+        }
+        grid.start_group("y:");
+        for s in &["four", "five"] {
+            grid.add((*s).into());
+        }
+
+        // The separator after the index column shrinks along with the rest.
+        let display = grid.fit_into_width(12).unwrap();
+        assert_eq!(display.width(), 12);
+        assert_eq!(display.to_string(), "x:\n1 one  three\n2 two  \ny:\n3 four five\n");
+    }
+
+    #[test]
+    fn rendered_in_chunks() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        grid.start_group("numbers");
+
+        for word in [ "one", "two", "three", "four", "five" ] {
+            grid.add(Cell::from(word));
+        }
+
+        grid.start_group("empty");
+
+        let display = grid.fit_into_width(10).unwrap();
+        let mut chunks = Vec::new();
+        let flow = display.render_chunks(2, |chunk| {
+            chunks.push(chunk.to_owned());
+            ControlFlow::Continue(())
+        });
+
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), display.to_string());
+
+        let mut calls = 0;
+        let flow = display.render_chunks(1, |_| {
+            calls += 1;
+            ControlFlow::Break(())
+        });
+
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn sticky_columns() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        for word in [ "seventy", "eleven", "three", "four", "be", "a", "c", "d" ] {
+            grid.add(Cell::from(word));
+        }
+
+        assert_eq!(grid.sticky_fit(12, 5).unwrap().layout().widths.len(), 2);
+
+        // Room for three columns isn’t enough to make up for the slack...
+        assert_eq!(grid.sticky_fit(16, 5).unwrap().layout().widths.len(), 2);
+        assert_eq!(grid.fit_into_width(16).unwrap().layout().widths.len(), 3);
+
+        // ...but room for all eight is.
+        assert_eq!(grid.sticky_fit(34, 5).unwrap().layout().widths.len(), 8);
+
+        // Going back down rearranges the grid once it no longer fits.
+        assert_eq!(grid.sticky_fit(24, 5).unwrap().layout().widths.len(), 4);
+        assert_eq!(grid.sticky_fit(18, 5).unwrap().layout().widths.len(), 4);
+        assert_eq!(grid.sticky_fit(17, 5).unwrap().layout().widths.len(), 3);
+        assert_eq!(grid.sticky_fit(15, 5).unwrap().layout().widths.len(), 3);
+        assert_eq!(grid.sticky_fit(13, 5).unwrap().layout().widths.len(), 2);
+    }
+
+    #[test]
+    fn measured_cells() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+
+        // The widths are taken as they are, so a cell that claims to be
+        // wider than it is doesn’t get padded out to the claimed width.
+        grid.add_measured(vec![ ("ab".to_owned(), 4), ("cd".to_owned(), 2) ]);
+        grid.add(Cell::raw("ef".into(), 2, Alignment::Right));
+
+        assert_eq!(grid.widest_cell(), Some((0, &Cell::raw("ab".into(), 4, Alignment::Left))));
+        assert_eq!(grid.fit_into_width(10).unwrap().to_string(), "ab cd ef\n");
+    }
+
+    #[test]
+    fn rows_needed() {
+        for direction in [ Direction::LeftToRight, Direction::TopToBottom ] {
+            let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), direction));
+            for word in [ "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten" ] {
+                grid.add(Cell::from(word));
+            }
+
+            for width in 0 .. 60 {
+                let fitted = grid.fit_into_width(width).map(|display| display.row_count());
+                assert_eq!(grid.rows_needed(width), fitted, "{:?} at width {}", direction, width);
+            }
+        }
+    }
+
+    #[test]
+    fn hidden_cells() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        grid.add("apple".into());
+        grid.start_group("B");
+        for s in &["banana", "blueberry", "cherry"] {
+            grid.add((*s).into());
+        }
+
+        grid.set_visible(1, false);
+        grid.set_visible(2, false);
+        assert_eq!(grid.widest_cell(), Some((1, &Cell::from("cherry"))));
+        assert_eq!(grid.original_index(1), 3);
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "apple\nB\ncherry\n");
+
+        // Showing the first cell of a group puts it back in that group.
+        grid.set_visible(1, true);
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "apple  \nB\nbanana cherry\n");
+
+        grid.set_visible(2, true);
+        assert_eq!(grid.fit_into_width(40).unwrap().to_string(), "apple  \nB\nbanana blueberry cherry\n");
+    }
+
+    #[test]
+    fn retained_cells() {
+        let mut one_by_one = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for s in &["fig", "banana", "kiwi", "cherry", "blueberry", "date"] {
+            one_by_one.add((*s).into());
+        }
+        let mut at_once = one_by_one.clone();
+
+        for (index, word) in [ "fig", "banana", "kiwi", "cherry", "blueberry", "date" ].into_iter().enumerate() {
+            one_by_one.set_visible(index, !word.contains('e'));
+        }
+        at_once.retain(|_, cell| !cell.contents().contains('e'));
+        assert_eq!(at_once, one_by_one);
+        assert_eq!(at_once.widest_cell(), Some((1, &Cell::from("banana"))));
+
+        // Showing and hiding cells keeps track of which is the first of the
+        // widest, without looking through them all.
+        one_by_one.set_visible(3, true);
+        assert_eq!(one_by_one.widest_cell(), Some((1, &Cell::from("banana"))));
+        one_by_one.set_visible(4, true);
+        one_by_one.set_visible(1, false);
+        at_once.retain(|index, _| index != 1 && index != 5);
+        assert_eq!(at_once, one_by_one);
+        assert_eq!(at_once.widest_cell(), Some((3, &Cell::from("blueberry"))));
+        assert_eq!(at_once.fit_into_width(40).unwrap().to_string(), "fig kiwi cherry blueberry\n");
+    }
+
+    #[test]
+    fn plain_output() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text(" | ".into()), Direction::TopToBottom)
+                                     .output_mode(OutputMode::Plain));
+        grid.start_group("fruit");
+        for s in &["apple", "banana", "cherry"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(16).unwrap();
+        assert_eq!(display.layout().widths.len(), 2);
+        assert_eq!(display.to_string(), "fruit\nRow 1, column 1: apple\nRow 1, column 2: cherry\nRow 2, column 1: banana\n");
+    }
+
+    #[cfg(feature = "fuzz")]
+    #[test]
+    fn fuzzed_wide_character_in_narrow_column() {
+        use std::fmt::Write;
+
+        // A zero maximum column width still lets the double-width character
+        // be wrapped onto a line of its own, which is then wider than its
+        // column, so it gets rendered unpadded rather than panicking.
+        let grid = Grid::from_fuzz_input(&[ 239, 70, 0, 242, 9, 51, 129, 129, 44, 10, 49, 128, 32, 101, 35 ]);
+        let display = grid.fit_into_width(184).unwrap();
+        assert!(write!(String::new(), "{}", display).is_ok());
+    }
+
+    #[test]
+    fn cell_wider_than_its_column() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .overflow(Overflow::Wrap));
+        for s in &["a", "一", "b"] {
+            grid.add((*s).into());
+        }
+
+        // A double-width character can’t be wrapped to fit a column one
+        // character wide, so it pushes the rest of the line along.
+        let display = Display::from_layout(&grid, Layout { num_lines: 1, widths: vec![ 1, 1, 1 ] }).unwrap();
+        assert_eq!(display.to_string(), "a 一 b\n");
+    }
+
+    #[test]
+    fn layout_from_a_slice() {
+        let options = GridOptions::new(Filling::Spaces(2), Direction::TopToBottom);
+        let cells: Vec<Cell> = [ "one", "two", "three", "four", "five" ].into_iter().map(Cell::from).collect();
+
+        let mut grid = Grid::new(options.clone());
+        for cell in &cells {
+            grid.add(cell.clone());
+        }
+
+        for width in 0 .. 30 {
+            let fitted = grid.fit_into_width(width).map(|display| display.layout().clone());
+            assert_eq!(layout_cells(&cells, &options, width), fitted);
+        }
+
+        // Wrapped cells are measured from their contents, which the slice
+        // still has, so they can’t be narrower than their widest character.
+        let options = options.max_column_width(1).overflow(Overflow::Wrap);
+        let cells: Vec<Cell> = [ "a", "日本", "b" ].into_iter().map(Cell::from).collect();
+
+        let mut grid = Grid::new(options.clone());
+        for cell in &cells {
+            grid.add(cell.clone());
+        }
+
+        for width in 0 .. 30 {
+            let fitted = grid.fit_into_width(width).map(|display| display.layout().clone());
+            assert_eq!(layout_cells(&cells, &options, width), fitted);
+        }
+        assert_eq!(layout_cells(&cells, &options, 20).unwrap().widths, vec![ 1, 2, 1 ]);
+    }
+
+    #[test]
+    fn tapered_gutter() {
+        let options = GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+                          .gutter(Gutter::Tapered { widest: 4, narrowest: 1 });
+
+        let mut grid = Grid::new(options.clone());
+        for s in &["one", "two"] {
+            grid.add((*s).into());
+        }
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "one    two\n");
+
+        let mut grid = Grid::new(options);
+        for s in &["three", "four", "be", "a", "c"] {
+            grid.add((*s).into());
+        }
+        assert_eq!(grid.fit_into_width(15).unwrap().to_string(), "three   be   c\nfour    a    \n");
+    }
+
+    #[test]
+    fn option_getters() {
+        let options = GridOptions::new(Filling::text(" | "), Direction::TopToBottom)
+                          .separator_at(1, Filling::spaces(4))
+                          .max_column_width(10)
+                          .pad_lines(true)
+                          .column_headers(|index| format!("#{}", index));
+
+        assert_eq!(options.get_filling(), &Filling::Text(" | ".into()));
+        assert_eq!(options.get_filling().width(), 3);
+        assert_eq!(options.get_separator_at(1), Some(&Filling::Spaces(4)));
+        assert_eq!(options.get_separator_at(0), None);
+        assert_eq!(options.get_direction(), Direction::TopToBottom);
+        assert_eq!(options.get_max_column_width(), Some(10));
+        assert!(options.get_pad_lines());
+        assert!(!options.get_continuous_rules());
+        assert_eq!(options.get_column_header(2), Some(Cell::from("#2")));
+        assert_eq!(options.get_gutter(), None);
+    }
+
+    #[test]
+    fn line_prefixes() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .line_prefix(LinePrefix::Text("> ".into())));
+        for s in &["one", "t", "three"] {
+            grid.add((*s).into());
+        }
+
+        // The prefix takes two of the ten columns, leaving room for only
+        // two columns of cells.
+        let display = grid.fit_into_width(10).unwrap();
+        assert_eq!(display.width(), 9);
+        assert_eq!(display.to_string(), "> one   t\n> three \n");
+
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .max_column_width(5)
+                                     .overflow(Overflow::Wrap)
+                                     .line_prefix(LinePrefix::custom(3, |row| format!("{}:", row + 1))));
+        for s in &["one two", "x", "four"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(11).unwrap();
+        assert_eq!(display.to_string(), " 1:one   x\n   two\n 2:four  \n");
+    }
+
+    #[test]
+    fn line_prefix_when_searching_upwards() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .line_prefix(LinePrefix::Text("> ".into())));
+        for s in &["one", "t", "three"] {
+            grid.add((*s).into());
+        }
+
+        // The second call starts from the number of lines the first found.
+        for _ in 0 .. 2 {
+            assert_eq!(grid.current_fit(10).unwrap().to_string(), "> one   t\n> three \n");
         }
-    }
 
-    pub fn reserve(&mut self, additional: usize) {
-        self.cells.reserve(additional);
+        // “three” fits into six columns, but not once the prefix is added.
+        assert!(grid.fit_best(&[ 10, 6 ]).is_some_and(|(width, _)| width == 10));
+        assert!(grid.fitter().widest_too_wide(6));
+        assert!(!grid.fitter().widest_too_wide(7));
     }
 
-    pub fn add(&mut self, cell: Cell) {
-        if cell.width > self.widest_cell_length {
-            self.widest_cell_length = cell.width;
+    #[test]
+    fn byte_budget() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .max_line_bytes(12));
+        for s in &["日本語", "の", "abcdef", "x"] {
+            grid.add((*s).into());
         }
-        self.width_sum += cell.width;
-        self.cell_count += 1;
-        self.cells.push(cell)
+
+        // The first line fits into ten columns, but takes up thirteen
+        // bytes, so it gets cut short with an ellipsis.
+        let display = grid.fit_into_width(10).unwrap();
+        assert_eq!(display.to_string(), "日本語…\nabcdef x\n");
     }
 
-    pub fn fit_into_width(&self, maximum_width: Width) -> Option<Display<'_>> {
-        self.width_dimensions(maximum_width)
-            .map(|dims| Display {
-                grid:       self,
-                dimensions: dims,
-            })
+    #[test]
+    fn byte_budget_with_spans() {
+        let spans = [ Span { cells: 1 .. 2, before: "\x1b[1;31m", after: "\x1b[0m" } ];
+        let render = |budget| {
+            let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                         .max_line_bytes(budget));
+            grid.add("abc".into());
+            grid.add("defgh".into());
+            grid.fit_into_width(20).unwrap().render_with_spans(&spans)
+        };
+
+        // A cell whose opening code doesn’t fit gets left out, and one that
+        // gets cut short still gets its closing code.
+        assert_eq!(render(12), "abc …\n");
+        assert_eq!(render(19), "abc \x1b[1;31md…\x1b[0m\n");
+        assert_eq!(render(20), "abc \x1b[1;31mdefgh\x1b[0m\n");
     }
 
-    fn column_widths(&self, num_lines: usize, num_columns: usize) -> Dimensions {
-        let mut widths: Vec<Width> = repeat(0).take(num_columns).collect();
-        for (index, cell) in self.cells.iter().enumerate() {
-            let index = match self.options.direction {
-                Direction::LeftToRight  => index % num_columns,
-                Direction::TopToBottom  => index / num_lines,
-            };
-            widths[index] = max(widths[index], cell.width);
+    #[test]
+    fn best_of_several_widths() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        for s in &["seventy", "eleven", "three", "four", "be", "a", "c", "d"] {
+            grid.add((*s).into());
         }
 
-        Dimensions { num_lines, widths }
-    }
+        for width in [ 10, 12, 16, 20, 34, 40 ] {
+            let (best_width, display) = grid.fit_best(&[ width ]).unwrap();
+            assert_eq!(best_width, width);
+            assert_eq!(display.to_string(), grid.fit_into_width(width).unwrap().to_string());
+        }
 
-    fn theoretical_max_num_lines(&self, maximum_width: usize) -> usize {
-        // TODO: Make code readable / efficient.
-        let mut theoretical_min_num_cols = 0;
-        let mut col_total_width_so_far = 0;
+        // Forty columns fit every cell on one line, with no room wasted.
+        let (best_width, display) = grid.fit_best(&[ 24, 10, 40, 16 ]).unwrap();
+        assert_eq!(best_width, 40);
+        assert_eq!(display.layout().num_lines, 1);
 
-        let mut cells = self.cells.clone();
-        cells.sort_unstable_by(|a, b| b.width.cmp(&a.width)); // Sort in reverse order
+        // Twenty and twenty-four columns both fit the grid into two lines of
+        // eighteen characters, so the narrower wins.
+        let (best_width, display) = grid.fit_best(&[ 24, 10, 20, 16 ]).unwrap();
+        assert_eq!(best_width, 20);
+        assert_eq!(display.layout().num_lines, 2);
 
-        for cell in &cells {
-            if cell.width + col_total_width_so_far <= maximum_width {
-                theoretical_min_num_cols += 1;
-                col_total_width_so_far += cell.width;
-            } else {
-                let mut theoretical_max_num_lines = self.cell_count / theoretical_min_num_cols;
-                if self.cell_count % theoretical_min_num_cols != 0 {
-                    theoretical_max_num_lines += 1;
-                }
-                return theoretical_max_num_lines;
-            }
-            col_total_width_so_far += self.options.filling.width()
-        }
-        1
+        assert!(grid.fit_best(&[ 2, 3 ]).is_none());
     }
 
-    fn width_dimensions(&self, maximum_width: Width) -> Option<Dimensions> {
-        if self.widest_cell_length > maximum_width {
-            // Largest cell is wider than maximum width; it is impossible to fit.
-            return None;
+    #[test]
+    fn transposed() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        for s in &["three", "seven", "a", "bb", "c"] {
+            grid.add((*s).into());
         }
 
-        if self.cell_count == 0 {
-            return Some(Dimensions { num_lines: 0, widths: Vec::new() });
-        }
+        let display = grid.fit_into_width(13).unwrap();
+        assert_eq!(display.to_string(), "three a  c\nseven bb \n");
 
-        if self.cell_count == 1 {
-            let the_cell = &self.cells[0];
-            return Some(Dimensions { num_lines: 1, widths: vec![ the_cell.width ] });
-        }
+        let transposed = display.transpose().unwrap();
+        assert_eq!(transposed.layout(), &Layout { num_lines: 2, widths: vec![ 5, 5, 1 ] });
+        assert_eq!(transposed.to_string(), "three seven a\nbb    c     \n");
+        assert_eq!(transposed.transpose().unwrap().to_string(), display.to_string());
 
-        let theoretical_max_num_lines = self.theoretical_max_num_lines(maximum_width);
-        if theoretical_max_num_lines == 1 {
-            return Some(Dimensions {
-                num_lines: 1,
-                widths: self.cells.clone().into_iter().map(|cell| cell.width).collect()
-            });
-        }
-        let mut smallest_dimensions_yet = None;
-        for num_lines in (1 .. theoretical_max_num_lines).rev() {
+        // Laid out the other way, the grid no longer fits.
+        let display = grid.fit_into_width(12).unwrap();
+        assert!(display.transpose().is_none());
+    }
 
-            // The number of columns is the number of cells divided by the number
-            // of lines, *rounded up*.
-            let mut num_columns = self.cell_count / num_lines;
-            if self.cell_count % num_lines != 0 {
-                num_columns += 1;
-            }
-            let total_separator_width = (num_columns - 1) * self.options.filling.width();
-            if maximum_width < total_separator_width {
-                continue;
-            }
+    #[test]
+    fn weighted_columns() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+                                     .column_weights([ 1, 2 ])
+                                     .pad_lines(true));
+        for s in &["three", "a", "b", "seven", "c", "d"] {
+            grid.add((*s).into());
+        }
 
-            // Remove the separator width from the available space.
-            let adjusted_width = maximum_width - total_separator_width;
+        // Three columns take up nine of the eleven, and the other two get
+        // shared between the first two. The second column’s share rounds
+        // down to one and the first’s to none, so the one left over goes
+        // to the first.
+        let display = grid.fit_into_width(11).unwrap();
+        assert_eq!(display.layout().widths, vec![ 6, 2, 1 ]);
+        assert_eq!(display.width(), 11);
+        assert_eq!(display.to_string(), "three  a  b\nseven  c  d\n");
+    }
 
-            let potential_dimensions = self.column_widths(num_lines, num_columns);
-            if potential_dimensions.widths.iter().sum::<Width>() < adjusted_width {
-                smallest_dimensions_yet = Some(potential_dimensions);
-            } else {
-                return smallest_dimensions_yet;
-            }
+    #[test]
+    fn justified() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight)
+                                     .justify(true));
+        for s in &["three", "a", "b", "seven", "c", "d"] {
+            grid.add((*s).into());
         }
 
-        None
+        // The grid is nine characters wide, so the two gaps share out the
+        // other three, with the first getting the odd one.
+        let display = grid.fit_into_width(12).unwrap();
+        assert_eq!(display.width(), 12);
+        assert_eq!(display.to_string(), "three | a| b\nseven | c| d\n");
     }
 
-}
-#[derive(PartialEq, Debug)]
-pub struct Display<'grid> {
-    grid: &'grid Grid,
-    dimensions: Dimensions,
-}
+    #[test]
+    fn debug_render() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+        for s in &["one", "two", "three", "a", "b"] {
+            grid.add((*s).into());
+        }
+        grid.cells[1].alignment = Alignment::Right;
 
-impl Display<'_> {
-    pub fn width(&self) -> Width {
-        self.dimensions.total_width(self.grid.options.filling.width())
+        let display = grid.fit_into_width(9).unwrap();
+        assert_eq!(display.debug_render(), "\
+            3 lines, widths [5, 1]\n\
+            |one··|a|\n\
+            |··two|b|\n\
+            |three| |\n");
     }
 
-    pub fn row_count(&self) -> usize {
-        self.dimensions.num_lines
+    #[test]
+    fn column_headers() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+                                     .column_headers(|x| format!("col{}", x + 1)));
+        for s in &["a", "b", "c", "d", "e"] {
+            grid.add((*s).into());
+        }
+
+        let display = grid.fit_into_width(10).unwrap();
+        assert_eq!(display.layout(), &Layout { num_lines: 3, widths: vec![ 4, 4 ] });
+        assert_eq!(display.to_string(), "col1 col2\na    d\nb    e\nc    \n");
     }
 
-    pub fn is_complete(&self) -> bool {
-        self.dimensions.widths.iter().all(|&x| x > 0)
+    #[test]
+    fn fit_into_rect() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for s in &["three", "a", "seven", "b", "eight", "c", "d"] {
+            grid.add((*s).into());
+        }
+
+        let fit = grid.fit_into_rect(8, 2);
+        assert_eq!((fit.shown(), fit.hidden()), (4, 3));
+        assert_eq!(fit.display().to_string(), "three a\nseven b\n");
+
+        let fit = grid.fit_into_rect(80, 1);
+        assert_eq!((fit.shown(), fit.hidden()), (7, 0));
+
+        let fit = grid.fit_into_rect(2, 5);
+        assert_eq!((fit.shown(), fit.hidden()), (0, 7));
+        assert_eq!(fit.display().to_string(), "");
     }
-}
 
+    #[test]
+    fn fit_into_rect_counts_every_line() {
+        let options = GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+                          .max_column_width(4)
+                          .overflow(Overflow::Wrap)
+                          .column_headers(|x| format!("c{}", x));
+        let mut grid = Grid::new(options.clone());
+        grid.start_group("odd");
+        for s in &["one", "three", "five"] {
+            grid.add((*s).into());
+        }
+        grid.start_group("even");
+        for s in &["two", "fourteen"] {
+            grid.add((*s).into());
+        }
 
-impl fmt::Display for Display<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        for y in 0 .. self.dimensions.num_lines {
-            for x in 0 .. self.dimensions.widths.len() {
-                let num = match self.grid.options.direction {
-                    Direction::LeftToRight  => y * self.dimensions.widths.len() + x,
-                    Direction::TopToBottom  => y + self.dimensions.num_lines * x,
-                };
+        // The column headers, both group headers and both wrapped rows.
+        let fit = grid.fit_into_rect(13, 7);
+        assert_eq!((fit.shown(), fit.hidden()), (5, 0));
+        assert_eq!(fit.display().to_string().lines().count(), 7);
 
-                // Abandon a line mid-way through if that’s where the cells end
-                if num >= self.grid.cells.len() {
-                    continue;
-                }
+        let fit = grid.fit_into_rect(13, 6);
+        assert_eq!((fit.shown(), fit.hidden()), (4, 1));
+        assert_eq!(fit.display().to_string(), "c0  c1   c2\nodd\none thre five\n    e\neven\ntwo \n");
 
-                let cell = &self.grid.cells[num];
-                if x == self.dimensions.widths.len() - 1 {
-                    match cell.alignment {
-                        Alignment::Left => {
-                            // The final column doesn’t need to have trailing spaces,
-                            // as long as it’s left-aligned.
-                            write!(f, "{}", cell.contents)?;
-                        },
-                        Alignment::Right => {
-                            let extra_spaces: usize = self.dimensions.widths[x] - cell.width;
-                            write!(f, "{}", pad_string(&cell.contents, extra_spaces, Alignment::Right))?;
-                        }
-                    }
-                }
-                else {
-                    assert!(self.dimensions.widths[x] >= cell.width);
-                    match (&self.grid.options.filling, cell.alignment) {
-                        (Filling::Spaces(n), Alignment::Left) => {
-                            let extra_spaces = self.dimensions.widths[x] - cell.width + n;
-                            write!(f, "{}", pad_string(&cell.contents, extra_spaces, cell.alignment))?;
-                        },
-                        (Filling::Spaces(n), Alignment::Right) => {
-                            let s = spaces(*n);
-                            let extra_spaces = self.dimensions.widths[x] - cell.width;
-                            write!(f, "{}{}", pad_string(&cell.contents, extra_spaces, cell.alignment), s)?;
-                        },
-                        (Filling::Text(ref t), _) => {
-                            let extra_spaces = self.dimensions.widths[x] - cell.width;
-                            write!(f, "{}{}", pad_string(&cell.contents, extra_spaces, cell.alignment), t)?;
-                        },
-                    }
+        let mut plain = Grid::new(options.output_mode(OutputMode::Plain).placeholder("-"));
+        for s in &["one", "three", "five", "two", "fourteen"] {
+            plain.add((*s).into());
+        }
+
+        // Every layout counts its lines the same way it renders them.
+        for grid in [ grid, plain ] {
+            for width in 1 .. 30 {
+                if let Some(display) = grid.fit_into_width(width) {
+                    assert_eq!(display.line_count(), display.to_string().lines().count());
                 }
             }
-
-            writeln!(f)?;
         }
-
-        Ok(())
     }
-}
 
-/// Pad a string with the given number of spaces.
-fn spaces(length: usize) -> String {
-    repeat(" ").take(length).collect()
-}
+    #[test]
+    fn added_columns() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+        grid.add_column(vec![ "Cargo.toml", "src", "README.md" ]);
 
+        let sizes: Vec<Cell> = [ "421", "4096" ].iter().map(|&size| {
+            let mut cell = Cell::from(size);
+            cell.alignment = Alignment::Right;
+            cell
+        }).collect();
+        grid.add_column(sizes);
 
-fn pad_string(string: &str, padding: usize, alignment: Alignment) -> String {
-    if alignment == Alignment::Left {
-        format!("{}{}", string, spaces(padding))
-    }
-    else {
-        format!("{}{}", spaces(padding), string)
+        let display = grid.fit_into_width(80).unwrap();
+        assert_eq!(display.to_string(), "Cargo.toml   421\nsrc         4096\nREADME.md   \n");
     }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
 
     #[test]
-    fn no_items() {
-        let grid = Grid::new(GridOptions {
-            direction:  Direction::TopToBottom,
-            filling:    Filling::Spaces(2),
-        });
-
-        let display = grid.fit_into_width(40).unwrap();
+    fn added_columns_keep_groups_and_hidden_cells() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        grid.start_group("files");
+        for name in [ "a", "b", "c" ] {
+            grid.add(Cell::from(name));
+        }
+        grid.set_visible(1, false);
 
-        assert_eq!(display.dimensions.num_lines, 0);
-        assert!(display.dimensions.widths.is_empty());
+        grid.add_column(vec![ "x", "y", "z" ]);
+        assert_eq!(grid.original_index(2), 3);
 
-        assert_eq!(display.width(), 0);
+        // The hidden cell is now the first one on the second row.
+        grid.set_visible(2, true);
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "files\na x\nb y\nc z\n");
     }
 
-
     #[test]
-    fn one_item() {
-        let mut grid = Grid::new(GridOptions {
-            direction:  Direction::TopToBottom,
-            filling:    Filling::Spaces(2),
-        });
+    fn numbers() {
+        for number in [ "0", "42", "-1,234.5", "+7", "1_000", "87%", "4.2K", "4.2 KiB", "12MB", "3.0" ] {
+            assert!(looks_like_number(number), "{:?} should look like a number", number);
+        }
 
-        grid.add(Cell::from("1"));
+        for other in [ "", "-", "abc", "1,,000", "1.2.3", "4.2X", "K", "12 apples", ".5", "1e5" ] {
+            assert!(!looks_like_number(other), "{:?} shouldn’t look like a number", other);
+        }
+    }
 
-        let display = grid.fit_into_width(40).unwrap();
+    #[test]
+    fn auto_aligned_numbers() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+                                     .auto_align_numbers(true));
+        for s in &["bananas", "apples", "12", "1.5K"] {
+            grid.add((*s).into());
+        }
 
-        assert_eq!(display.dimensions.num_lines, 1);
-        assert_eq!(display.dimensions.widths, vec![ 1 ]);
+        assert_eq!(grid.fit_into_width(13).unwrap().to_string(), "bananas   12\napples  1.5K\n");
+    }
 
-        assert_eq!(display.width(), 1);
+    #[test]
+    fn numbers_from_integers() {
+        assert_eq!(Cell::from_number(1_234_567, NumberFormat::Grouped(',')).contents, "1,234,567");
+        assert_eq!(Cell::from_number(-234_567, NumberFormat::Grouped(' ')).contents, "-234 567");
+        assert_eq!(Cell::from_number(999u16, NumberFormat::Grouped(',')).contents, "999");
+        assert_eq!(Cell::from_number(u64::MAX, NumberFormat::Plain).contents, "18446744073709551615");
+
+        let cell = Cell::from_number(1000, NumberFormat::Grouped('.'));
+        assert_eq!((cell.contents.as_str(), cell.width, cell.alignment), ("1.000", 5, Alignment::Right));
     }
 
     #[test]
-    fn one_item_exact_width() {
-        let mut grid = Grid::new(GridOptions {
-            direction:  Direction::TopToBottom,
-            filling:    Filling::Spaces(2),
-        });
+    fn sizes_from_bytes() {
+        let size = |bytes, units| Cell::from_bytes(bytes, units).contents;
 
-        grid.add(Cell::from("1234567890"));
+        assert_eq!(size(0, BinaryOrDecimal::Binary), "0 B  ");
+        assert_eq!(size(1023, BinaryOrDecimal::Binary), "1023 B  ");
+        assert_eq!(size(4300, BinaryOrDecimal::Binary), "4.2 KiB");
+        assert_eq!(size(4300, BinaryOrDecimal::Decimal), "4.3 kB");
+        assert_eq!(size(999, BinaryOrDecimal::Decimal), "999 B ");
+        assert_eq!(size(999_999, BinaryOrDecimal::Decimal), "1.0 MB");
+        assert_eq!(size(u64::MAX, BinaryOrDecimal::Binary), "16.0 EiB");
 
-        let display = grid.fit_into_width(10).unwrap();
+        assert_eq!(Cell::from_bytes(4300, BinaryOrDecimal::Binary).alignment, Alignment::Right);
+    }
 
-        assert_eq!(display.dimensions.num_lines, 1);
-        assert_eq!(display.dimensions.widths, vec![ 10 ]);
+    #[test]
+    fn durations() {
+        let duration = |seconds| Cell::from_duration(Duration::from_secs(seconds)).contents;
 
-        assert_eq!(display.width(), 10);
+        assert_eq!(duration(0), "00:00:00");
+        assert_eq!(duration(3723), "01:02:03");
+        assert_eq!(duration(360_000), "100:00:00");
+        assert_eq!(Cell::from_duration(Duration::from_millis(999)).contents, "00:00:00");
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
-    fn one_item_just_over() {
-        let mut grid = Grid::new(GridOptions {
-            direction:  Direction::TopToBottom,
-            filling:    Filling::Spaces(2),
-        });
+    fn timestamps() {
+        // 2001-09-09 01:46:40 UTC, which is in the same month in every time
+        // zone.
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let cell = Cell::from_timestamp(time, "%Y-%m");
+        assert_eq!(cell.contents, "2001-09");
+        assert_eq!(cell.alignment, Alignment::Right);
+    }
 
-        grid.add(Cell::from("1234567890!"));
+    #[test]
+    fn huge_separator() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(100), Direction::LeftToRight));
 
-        assert_eq!(grid.fit_into_width(10), None);
+        grid.add("a".into());
+        grid.add("b".into());
+
+        assert_eq!(grid.fit_into_width(99), None);
     }
 
     #[test]
-    fn two_small_items() {
-        let mut grid = Grid::new(GridOptions {
-            direction:  Direction::TopToBottom,
-            filling:    Filling::Spaces(2),
-        });
+    fn huge_yet_unused_separator() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(100), Direction::LeftToRight));
 
-        grid.add(Cell::from("1"));
-        grid.add(Cell::from("2"));
+        grid.add("abcd".into());
 
-        let display = grid.fit_into_width(40).unwrap();
+        let display = grid.fit_into_width(99).unwrap();
 
         assert_eq!(display.dimensions.num_lines, 1);
-        assert_eq!(display.dimensions.widths, vec![ 1, 1 ]);
+        assert_eq!(display.dimensions.widths, vec![ 4 ]);
 
-        assert_eq!(display.width(), 1 + 2 + 1);
+        assert_eq!(display.width(), 4);
     }
 
     #[test]
-    fn two_medium_size_items() {
-        let mut grid = Grid::new(GridOptions {
-            direction:  Direction::TopToBottom,
-            filling:    Filling::Spaces(2),
-        });
+    fn shrinking_to_fit() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        grid.reserve(100);
+        for word in [ "one", "two", "three" ] {
+            let mut contents = String::with_capacity(64);
+            contents.push_str(word);
+            grid.add(Cell::from(contents));
+        }
 
-        grid.add(Cell::from("hello there"));
-        grid.add(Cell::from("how are you today?"));
+        // Short compact strings are moved inline, so only plain strings keep
+        // their spare capacity on the heap.
+        let spare = if cfg!(feature = "compact_str") { 0 } else { 3 * 64 };
+        let before = grid.memory_footprint();
+        assert!(before >= 100 * std::mem::size_of::<Cell>() + spare);
 
-        let display = grid.fit_into_width(40).unwrap();
+        grid.shrink_to_fit();
+        let after = grid.memory_footprint();
+        assert!(after < before);
+        assert!(after <= 3 * std::mem::size_of::<Cell>() + 2 * std::mem::size_of::<(Width, usize)>() + 11);
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "one two three\n");
+    }
 
-        assert_eq!(display.dimensions.num_lines, 1);
-        assert_eq!(display.dimensions.widths, vec![ 11, 18 ]);
+    #[test]
+    #[cfg(feature = "compact_str")]
+    fn short_contents_kept_inline() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for word in [ "one", "two", "three" ] {
+            grid.add(Cell::from(word));
+        }
+        grid.shrink_to_fit();
 
-        assert_eq!(display.width(), 11 + 2 + 18);
+        assert_eq!(grid.memory_footprint(), 3 * std::mem::size_of::<Cell>() + 2 * std::mem::size_of::<(Width, usize)>());
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "one two three\n");
     }
 
-
     #[test]
-    fn two_big_items() {
-        let mut grid = Grid::new(GridOptions {
-            direction:  Direction::TopToBottom,
-            filling:    Filling::Spaces(2),
-        });
+    fn rendered_into_a_buffer() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for word in [ "one", "t", "three" ] {
+            grid.add(Cell::from(word));
+        }
 
-        grid.add(Cell::from("nuihuneihsoenhisenouiuteinhdauisdonhuisudoiosadiuohnteihaosdinhteuieudi"));
-        grid.add(Cell::from("oudisnuthasuouneohbueobaugceoduhbsauglcobeuhnaeouosbubaoecgueoubeohubeo"));
+        let mut buf = String::from("left over from the last frame");
+        grid.fit_into_width(20).unwrap().render_into(&mut buf);
+        assert_eq!(buf, "one t three\n");
 
-        assert_eq!(grid.fit_into_width(40), None);
+        let capacity = buf.capacity();
+        grid.fit_into_width(8).unwrap().render_into(&mut buf);
+        assert_eq!(buf, "one   t\nthree \n");
+        assert_eq!(buf.capacity(), capacity);
     }
 
-
     #[test]
-    fn that_example_from_earlier() {
-        let mut grid = Grid::new(GridOptions {
-            filling:    Filling::Spaces(1),
-            direction:  Direction::LeftToRight,
-        });
+    fn rendered_window() {
+        let grid = Grid::from_rows(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight), vec![
+            vec![ "name", "a", "bb", "ccc", "d" ],
+            vec![ "x", "1", "22", "333", "4" ],
+        ]);
+        let display = grid.fit_into_width(80).unwrap();
 
-        for s in &["one", "two", "three", "four", "five", "six", "seven",
-                   "eight", "nine", "ten", "eleven", "twelve"]
-        {
-            grid.add(Cell::from(*s));
+        assert_eq!(display.render_window(2, 3, 1), "name|bb|ccc\nx   |22|333\n");
+        assert_eq!(display.render_window(0, 1, 1), "name|a\nx   |1\n");
+        assert_eq!(display.render_window(4, 10, 0), "d\n4\n");
+        assert_eq!(display.render_window(0, usize::MAX, 0), display.to_string());
+    }
+
+    #[test]
+    fn grouped_columns() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Text("│".into()), Direction::LeftToRight)
+            .group_columns(2, Filling::Text(" ┃ ".into())));
+        for word in [ "abc", "b", "c", "def", "e", "f" ] {
+            grid.add(Cell::from(word));
         }
 
-        let bits = "one  two three  four\nfive six seven  eight\nnine ten eleven twelve\n";
-        assert_eq!(grid.fit_into_width(24).unwrap().to_string(), bits);
-        assert_eq!(grid.fit_into_width(24).unwrap().row_count(), 3);
+        // One line needs 6 cells, 2 group separators, and 3 plain ones,
+        // which is 19 columns wide.
+        assert_eq!(grid.fit_into_width(10).unwrap().to_string(), "abc│b ┃ c\ndef│e ┃ f\n");
+        assert_eq!(grid.fit_into_width(19).unwrap().to_string(), "abc│b ┃ c│def ┃ e│f\n");
     }
 
     #[test]
-    fn number_grid_with_pipe() {
-        let mut grid = Grid::new(GridOptions {
-            filling:    Filling::Text("|".into()),
-            direction:  Direction::LeftToRight,
-        });
+    fn checked_add() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight)
+            .max_cell_width(5));
 
-        for s in &["one", "two", "three", "four", "five", "six", "seven",
-                   "eight", "nine", "ten", "eleven", "twelve"]
-        {
-            grid.add(Cell::from(*s));
-        }
+        assert_eq!(grid.try_add(Cell::from("short")), Ok(()));
 
-        let bits = "one |two|three |four\nfive|six|seven |eight\nnine|ten|eleven|twelve\n";
-        assert_eq!(grid.fit_into_width(24).unwrap().to_string(), bits);
-        assert_eq!(grid.fit_into_width(24).unwrap().row_count(), 3);
-    }
+        let error = grid.try_add(Cell::from("far too long")).unwrap_err();
+        assert_eq!(error.cell.contents, "far too long");
+        assert_eq!(error.to_string(), "the cell is 12 columns wide, more than the maximum of 5");
 
+        let (cut, width) = truncate_string(&error.cell.contents, error.maximum, TruncateSide::Right, EmojiWidth::Measured);
+        grid.try_add(Cell::raw(cut, width, Alignment::Left)).unwrap();
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "short far …\n");
+    }
 
     #[test]
-    fn numbers_right() {
-        let mut grid = Grid::new(GridOptions {
-            filling:    Filling::Spaces(1),
-            direction:  Direction::LeftToRight,
-        });
-
-        for s in &["one", "two", "three", "four", "five", "six", "seven",
-                   "eight", "nine", "ten", "eleven", "twelve"]
-        {
-            let mut cell = Cell::from(*s);
-            cell.alignment = Alignment::Right;
-            grid.add(cell);
+    fn layout_bytes() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        for word in [ "three", "four", "be", "a", "c" ] {
+            grid.add(Cell::from(word));
         }
 
-        let bits = " one two  three   four\nfive six  seven  eight\nnine ten eleven twelve\n";
-        assert_eq!(grid.fit_into_width(24).unwrap().to_string(), bits);
-        assert_eq!(grid.fit_into_width(24).unwrap().row_count(), 3);
+        let display = grid.fit_into_width(12).unwrap();
+        let bytes = display.dimensions.to_bytes();
+        assert_eq!(bytes, vec![ 1, 2, 3, 5, 2, 1 ]);
+
+        let layout = Layout::from_bytes(&bytes).unwrap();
+        assert_eq!(Display::from_layout(&grid, layout).unwrap().to_string(), display.to_string());
+
+        let wide = Layout { num_lines: 300, widths: vec![ 0, 128 ] };
+        assert_eq!(Layout::from_bytes(&wide.to_bytes()), Some(wide));
+        assert_eq!(Layout::from_bytes(&bytes[.. 4]), None);
+        assert_eq!(Layout::from_bytes(&[ 2, 1, 0 ]), None);
+
+        let too_tall = Layout::from_bytes(&[ 1, 0x80, 0x89, 0x7a, 1, 5 ]).unwrap();
+        assert_eq!(too_tall.num_lines, 2_000_000);
+        assert!(Display::from_layout(&grid, too_tall).is_none());
+
+        let empty = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom));
+        assert!(Display::from_layout(&empty, Layout { num_lines: 3, widths: vec![] }).is_none());
+        assert!(Display::from_layout(&empty, Layout { num_lines: 0, widths: vec![] }).is_some());
     }
 
     #[test]
-    fn huge_separator() {
-        let mut grid = Grid::new(GridOptions {
-            filling:    Filling::Spaces(100),
-            direction:  Direction::LeftToRight,
-        });
+    fn padding_after_separator() {
+        let options = GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight);
+        let rows = || vec![
+            vec![ Cell::from("ab"), Cell::from("1"), Cell::from("x") ],
+            vec![ Cell::from("c"), Cell::from("234"), Cell::from("y") ],
+        ];
+        let right_align = |mut grid: Grid| {
+            for index in [ 1, 4 ] {
+                grid.cells[index].alignment = Alignment::Right;
+            }
+            grid
+        };
 
-        grid.add("a".into());
-        grid.add("b".into());
+        let grid = right_align(Grid::from_rows(options.clone(), rows()));
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "ab|  1|x\nc |234|y\n");
 
-        assert_eq!(grid.fit_into_width(99), None);
+        let grid = right_align(Grid::from_rows(options.padding_placement(PaddingPlacement::AfterSeparator), rows()));
+        assert_eq!(grid.fit_into_width(20).unwrap().to_string(), "ab|  1|x\nc| 234|y\n");
     }
 
     #[test]
-    fn huge_yet_unused_separator() {
-        let mut grid = Grid::new(GridOptions {
-            filling:    Filling::Spaces(100),
-            direction:  Direction::LeftToRight,
-        });
+    fn one_line_checks() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight));
+        assert!(grid.is_empty());
+        assert!(grid.fits_on_one_line(0));
 
-        grid.add("abcd".into());
+        for word in [ "one", "t", "three" ] {
+            grid.add(Cell::from(word));
+        }
 
-        let display = grid.fit_into_width(99).unwrap();
+        assert!(!grid.is_empty());
+        assert!(grid.fits_on_one_line(13));
+        assert!(!grid.fits_on_one_line(12));
+        assert_eq!(grid.fit_into_width(13).unwrap().row_count(), 1);
+        assert_eq!(grid.fit_into_width(9).unwrap().row_count(), 2);
 
-        assert_eq!(display.dimensions.num_lines, 1);
-        assert_eq!(display.dimensions.widths, vec![ 4 ]);
+        grid.set_visible(0, false);
+        grid.set_visible(1, false);
+        grid.set_visible(2, false);
+        assert!(grid.is_empty());
+    }
 
-        assert_eq!(display.width(), 4);
+    #[test]
+    fn column_granularity() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(0), Direction::LeftToRight)
+            .column_granularity(8));
+        for word in [ "twelve-ish", "a", "eleven-ish", "b" ] {
+            grid.add(Cell::from(word));
+        }
+
+        // Two columns of 16 and 8 need 24 columns, rather than the 11 the
+        // cells themselves take up.
+        let display = grid.fit_into_width(25).unwrap();
+        assert_eq!(display.dimensions.widths, vec![ 16, 8 ]);
+        assert_eq!(display.to_string(), "twelve-ish      a\neleven-ish      b\n");
+        assert_eq!(grid.fit_into_width(24), None);
     }
-}
 
+    #[test]
+    fn column_granularity_wider_than_width() {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::TopToBottom)
+            .column_granularity(8));
+        for word in [ "one", "two", "six" ] {
+            grid.add(Cell::from(word));
+        }
+
+        // Every cell fits, but not once it’s rounded up to eight columns.
+        assert_eq!(grid.fit_into_width(5), None);
+        assert_eq!(grid.rows_needed(5), None);
+    }
 
+    #[test]
+    fn annotated_rows() {
+        let options = GridOptions::new(Filling::Spaces(2), Direction::LeftToRight)
+            .annotations([ "Jan 1", "", "yesterday" ]);
+        let grid = Grid::from_rows(options, vec![
+            vec![ "Cargo.toml", "293" ],
+            vec![ "src" ],
+            vec![ "README", "1024" ],
+        ]);
+
+        // The columns take up 16 of the 30 columns, and the widest
+        // annotation another 11 with the filling before it.
+        let display = grid.fit_into_width(30).unwrap();
+        assert_eq!(display.to_string(), "\
+            Cargo.toml  293          Jan 1\n\
+            src         \n\
+            README      1024     yesterday\n");
 
+        assert_eq!(grid.fit_into_width(26), None);
+        assert_eq!(grid.fit_into_width(27).unwrap().to_string().lines().next(), Some("Cargo.toml  293       Jan 1"));
+    }
+}
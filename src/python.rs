@@ -0,0 +1,114 @@
+//! Bindings for Python, built with PyO3, so scripts can lay out grids
+//! without shelling out to a program that does. The module is called
+//! `rc_column`, and has `Cell` and `Grid` classes:
+//!
+//! ```python
+//! from rc_column import Cell, Grid
+//!
+//! grid = Grid(separator="  ")
+//! for name in ["one", "two", Cell("three", align_right=True)]:
+//!     grid.add(name)
+//! print(grid.fit_into_width(80), end="")
+//! ```
+//!
+//! Build the extension module with a tool such as `maturin`, enabling the
+//! `python` feature.
+
+use pyo3::prelude::*;
+
+use super::{Alignment, Cell, Direction, Filling, Grid, GridOptions, Width};
+
+
+/// A cell to put in a grid, called `Cell` on the Python side.
+#[pyclass(name = "Cell", module = "rc_column")]
+#[derive(Debug, Clone)]
+pub struct PyCell {
+    cell: Cell,
+}
+
+#[pymethods]
+impl PyCell {
+    #[new]
+    #[pyo3(signature = (contents, align_right = false))]
+    fn new(contents: &str, align_right: bool) -> Self {
+        let mut cell = Cell::from(contents);
+        if align_right {
+            cell.alignment = Alignment::Right;
+        }
+
+        Self { cell }
+    }
+
+    #[getter]
+    fn contents(&self) -> &str {
+        &self.cell.contents
+    }
+
+    #[getter]
+    fn width(&self) -> Width {
+        self.cell.width
+    }
+}
+
+/// Either a cell or a string to make one from.
+#[derive(Debug, FromPyObject)]
+enum CellArg {
+    Cell(PyCell),
+    Text(String),
+}
+
+/// A grid of cells, called `Grid` on the Python side.
+#[pyclass(name = "Grid", module = "rc_column")]
+#[derive(Debug)]
+pub struct PyGrid {
+    grid: Grid,
+}
+
+#[pymethods]
+impl PyGrid {
+    #[new]
+    #[pyo3(signature = (separator = "  ", top_to_bottom = false))]
+    fn new(separator: &str, top_to_bottom: bool) -> Self {
+        let direction = if top_to_bottom { Direction::TopToBottom } else { Direction::LeftToRight };
+        let options = GridOptions::new(Filling::Text(separator.into()), direction);
+        Self { grid: Grid::new(options) }
+    }
+
+    /// Adds a cell, or a string, to the end of the grid.
+    fn add(&mut self, cell: CellArg) {
+        match cell {
+            CellArg::Cell(cell)  => self.grid.add(cell.cell),
+            CellArg::Text(text)  => self.grid.add(text.into()),
+        }
+    }
+
+    /// Renders the grid fitted into the given width, or returns `None` if
+    /// it doesn’t fit.
+    fn fit_into_width(&self, width: Width) -> Option<String> {
+        self.grid.fit_into_width(width).map(|display| display.to_string())
+    }
+}
+
+#[pymodule]
+fn rc_column(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyCell>()?;
+    module.add_class::<PyGrid>()?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fit_cells_and_strings() {
+        let mut grid = PyGrid::new(" | ", false);
+        grid.add(CellArg::Text("one".into()));
+        grid.add(CellArg::Cell(PyCell::new("22", true)));
+        grid.add(CellArg::Text("three".into()));
+
+        assert_eq!(grid.fit_into_width(80).as_deref(), Some("one | 22 | three\n"));
+        assert_eq!(grid.fit_into_width(4), None);
+    }
+}
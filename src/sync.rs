@@ -0,0 +1,104 @@
+//! A grid that can be shared between threads, so one thread can keep adding
+//! cells to it while another fits and renders what’s there so far, such as
+//! a live display of search results as they stream in.
+
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::{Cell, Display, Grid, GridOptions, Width};
+
+
+/// A handle to a grid shared between threads. Cloning the handle shares the
+/// same grid, rather than copying it.
+///
+/// Cells are added under a write lock, and the grid is fitted and rendered
+/// under a read lock, so rendering never sees a half-added cell.
+#[derive(Debug, Clone)]
+pub struct SyncGrid {
+    grid: Arc<RwLock<Grid>>,
+}
+
+impl SyncGrid {
+    pub fn new(options: GridOptions) -> Self {
+        Self::from(Grid::new(options))
+    }
+
+    /// Adds a cell to the end of the grid.
+    pub fn add(&self, cell: Cell) {
+        self.write().add(cell);
+    }
+
+    /// Adds every cell to the end of the grid, taking the lock only once.
+    pub fn extend(&self, cells: impl IntoIterator<Item = Cell>) {
+        let mut grid = self.write();
+        for cell in cells {
+            grid.add(cell);
+        }
+    }
+
+    /// Fits the grid, as it is right now, into the given width, and passes
+    /// the result to the given function. Other threads can’t add cells until
+    /// the function returns, so it should render and get out of the way.
+    pub fn with_fit<R>(&self, maximum_width: Width, render: impl FnOnce(Option<Display<'_>>) -> R) -> R {
+        let grid = self.read();
+        render(grid.fit_into_width(maximum_width))
+    }
+
+    /// Fits the grid, as it is right now, into the given width and renders
+    /// it, or returns `None` if it doesn’t fit.
+    pub fn render(&self, maximum_width: Width) -> Option<String> {
+        self.with_fit(maximum_width, |display| display.map(|display| display.to_string()))
+    }
+
+    /// Returns a copy of the grid as it is right now, which can be fitted
+    /// and rendered without holding up other threads.
+    pub fn snapshot(&self) -> Grid {
+        self.read().clone()
+    }
+
+    // A thread that panicked while holding the lock can’t have left the grid
+    // half-updated, as adding a cell doesn’t panic part-way through, so a
+    // poisoned lock is as good as any other.
+
+    fn read(&self) -> RwLockReadGuard<'_, Grid> {
+        self.grid.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Grid> {
+        self.grid.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl From<Grid> for SyncGrid {
+    fn from(grid: Grid) -> Self {
+        Self { grid: Arc::new(RwLock::new(grid)) }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Direction, Filling};
+    use std::thread;
+
+    #[test]
+    fn added_from_another_thread() {
+        let grid = SyncGrid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+
+        let producer = {
+            let grid = grid.clone();
+            thread::spawn(move || {
+                for s in ["three", "a", "four"] {
+                    grid.add(s.into());
+                }
+            })
+        };
+
+        producer.join().unwrap();
+        grid.extend(["b".into()]);
+
+        assert_eq!(grid.render(80).unwrap(), "three a four b\n");
+        assert_eq!(grid.with_fit(9, |display| display.unwrap().row_count()), 2);
+        assert_eq!(grid.snapshot().fit_into_width(80).unwrap().row_count(), 1);
+    }
+}
@@ -0,0 +1,519 @@
+//! Tables with a fixed set of columns, sized using the same machinery as
+//! grids. Where a grid decides how many columns to split its cells into, a
+//! table already knows: it only has to work out how wide each one should be.
+
+use std::borrow::Cow;
+use std::cmp::max;
+use std::fmt;
+
+use super::{line_of, pad_string, pad_string_trimmed, spaces, truncate_string, widest_cluster, wrap_string};
+use super::{Alignment, Cell, EmojiWidth, Filling, Layout, Overflow, TruncateSide, Width};
+
+
+/// How the cells in one column of a table get displayed.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Column {
+
+    /// Overrides the alignment of every cell in the column, if set.
+    alignment: Option<Alignment>,
+
+    /// What to do with cells that are too wide for the column.
+    overflow: Overflow,
+
+    /// Overrides the padding character of every cell in the column, if set.
+    pad_char: Option<char>,
+
+    /// Which part of a cell gets cut out when it’s truncated.
+    truncate_side: TruncateSide,
+
+    /// The order the table is sorted in by this column, if it is.
+    sort_order: Option<SortOrder>,
+}
+
+impl Default for Column {
+    fn default() -> Self {
+        Self { alignment: None, overflow: Overflow::Truncate, pad_char: None, truncate_side: TruncateSide::Right, sort_order: None }
+    }
+}
+
+/// Which way a table is sorted by one of its columns, shown by a glyph at
+/// the end of the column’s header.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum SortOrder {
+
+    /// Smallest first, shown by `▲`.
+    Ascending,
+
+    /// Largest first, shown by `▼`.
+    Descending,
+}
+
+impl SortOrder {
+    fn glyph(self) -> char {
+        match self {
+            SortOrder::Ascending   => '▲',
+            SortOrder::Descending  => '▼',
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Table {
+    filling: Filling,
+    columns: Vec<Column>,
+    headers: Option<Vec<Cell>>,
+    rows: Vec<Vec<Cell>>,
+
+    /// How wide emoji sequences are taken to be, as with grids.
+    emoji_width: EmojiWidth,
+}
+
+impl Table {
+    pub fn new(filling: Filling) -> Self {
+        Self {
+            filling,
+            columns: Vec::new(),
+            headers: None,
+            rows: Vec::new(),
+            emoji_width: EmojiWidth::Measured,
+        }
+    }
+
+    /// Sets the cells to display above the first row.
+    pub fn set_headers<C: Into<Cell>>(&mut self, headers: impl IntoIterator<Item = C>) {
+        self.headers = Some(headers.into_iter().map(|cell| self.measured(cell.into())).collect());
+    }
+
+    /// Adds a row of cells to the bottom of the table. Rows with fewer cells
+    /// than there are columns are left blank on the right.
+    pub fn add_row<C: Into<Cell>>(&mut self, row: impl IntoIterator<Item = C>) {
+        let row = row.into_iter().map(|cell| self.measured(cell.into())).collect();
+        self.rows.push(row);
+    }
+
+    /// Sets how wide emoji sequences are taken to be, measuring the cells
+    /// already in the table again, along with any added later.
+    pub fn set_emoji_width(&mut self, emoji_width: EmojiWidth) {
+        self.emoji_width = emoji_width;
+        if emoji_width != EmojiWidth::Measured {
+            for cell in self.headers.iter_mut().chain(self.rows.iter_mut()).flatten() {
+                cell.width = emoji_width.width(&cell.contents);
+            }
+        }
+    }
+
+    /// Measures a cell again if the emoji width policy measures emoji
+    /// differently from `Cell::from`.
+    fn measured(&self, mut cell: Cell) -> Cell {
+        if self.emoji_width != EmojiWidth::Measured {
+            cell.width = self.emoji_width.width(&cell.contents);
+        }
+
+        cell
+    }
+
+    /// Aligns every cell in the given column, overriding their own alignment.
+    pub fn set_alignment(&mut self, column: usize, alignment: Alignment) {
+        self.column_mut(column).alignment = Some(alignment);
+    }
+
+    /// Sets which part of the cells in the given column gets cut out when
+    /// they’re truncated.
+    pub fn set_truncate_side(&mut self, column: usize, side: TruncateSide) {
+        self.column_mut(column).truncate_side = side;
+    }
+
+    /// Pads every cell in the given column with the given character,
+    /// overriding their own padding character, such as `.` for leaders.
+    pub fn set_pad_char(&mut self, column: usize, pad_char: char) {
+        self.column_mut(column).pad_char = Some(pad_char);
+    }
+
+    /// Sets what happens to cells in the given column when the table has to
+    /// be squeezed to fit into a width. Columns truncate by default.
+    pub fn set_overflow(&mut self, column: usize, overflow: Overflow) {
+        self.column_mut(column).overflow = overflow;
+    }
+
+    /// Marks the given column as the one the rows are sorted by, or not, by
+    /// putting a glyph at the end of its header, after a space. The glyph
+    /// counts towards the width of the column. The rows themselves are left
+    /// in the order they were added, so sorting them is up to the caller,
+    /// and other columns keep their glyphs until they’re cleared.
+    pub fn set_sort_order(&mut self, column: usize, sort_order: Option<SortOrder>) {
+        self.column_mut(column).sort_order = sort_order;
+    }
+
+    /// Returns the order the rows are marked as sorted in by the given
+    /// column, if they are.
+    pub fn sort_order(&self, column: usize) -> Option<SortOrder> {
+        self.column(column).sort_order
+    }
+
+    fn column_mut(&mut self, column: usize) -> &mut Column {
+        if self.columns.len() <= column {
+            self.columns.resize(column + 1, Column::default());
+        }
+
+        &mut self.columns[column]
+    }
+
+    fn column(&self, column: usize) -> Column {
+        self.columns.get(column).copied().unwrap_or_default()
+    }
+
+    /// Returns the header row, if there is one, followed by every other row.
+    /// Headers of sorted columns get their glyphs added.
+    fn all_rows(&self) -> impl Iterator<Item = Cow<'_, [Cell]>> {
+        let headers = self.headers.as_ref().map(|headers| {
+            if self.columns.iter().all(|column| column.sort_order.is_none()) {
+                return Cow::Borrowed(&headers[..]);
+            }
+
+            headers.iter().enumerate()
+                .map(|(x, header)| match self.column(x).sort_order {
+                    Some(sort_order)  => {
+                        let mut header = header.clone();
+                        header.contents.push(' ');
+                        header.contents.push(sort_order.glyph());
+                        header.width += 2;
+                        header
+                    },
+                    None              => header.clone(),
+                })
+                .collect()
+        });
+
+        headers.into_iter().chain(self.rows.iter().map(|row| Cow::Borrowed(&row[..])))
+    }
+
+    /// The widths each column would need to display every cell in full.
+    fn natural_dimensions(&self) -> Layout {
+        let mut widths: Vec<Width> = Vec::new();
+        let mut num_lines = 0;
+
+        for row in self.all_rows() {
+            if widths.len() < row.len() {
+                widths.resize(row.len(), 0);
+            }
+
+            for (index, cell) in row.iter().enumerate() {
+                widths[index] = max(widths[index], cell.width);
+            }
+
+            num_lines += 1;
+        }
+
+        Layout { num_lines, widths }
+    }
+
+    /// The narrowest each column can be squeezed to: one character, or for
+    /// columns that wrap, the widest character in any of their cells, as
+    /// wrapping can’t split one.
+    fn minimum_widths(&self, num_columns: usize) -> Vec<Width> {
+        let mut minimums = vec![ 1; num_columns ];
+        for row in self.all_rows() {
+            for (x, cell) in row.iter().enumerate() {
+                if self.column(x).overflow == Overflow::Wrap {
+                    minimums[x] = max(minimums[x], widest_cluster(&cell.contents, self.emoji_width));
+                }
+            }
+        }
+
+        minimums
+    }
+
+    /// Returns a displayable table that fits into the given width, squeezing
+    /// the widest columns first if the table is too wide as it is. Returns
+    /// `None` if there isn’t enough room to give every column at least one
+    /// character, or the widest character in a column that wraps.
+    pub fn fit_into_width(&self, maximum_width: Width) -> Option<TableDisplay<'_>> {
+        let mut dimensions = self.natural_dimensions();
+        let minimums = self.minimum_widths(dimensions.widths.len());
+        let separator_width = self.filling.width();
+
+        while dimensions.total_width(separator_width) > maximum_width {
+            let (widest, _) = dimensions.widths.iter_mut().zip(&minimums)
+                .filter(|(width, minimum)| **width > **minimum)
+                .max_by_key(|(width, _)| **width)?;
+
+            *widest -= 1;
+        }
+
+        Some(TableDisplay { table: self, dimensions })
+    }
+}
+
+
+#[derive(PartialEq, Debug)]
+pub struct TableDisplay<'table> {
+    table: &'table Table,
+    dimensions: Layout,
+}
+
+impl TableDisplay<'_> {
+    pub fn width(&self) -> Width {
+        self.dimensions.total_width(self.table.filling.width())
+    }
+
+    /// Returns the number of rows, including the header row if there is one.
+    /// Rows whose cells wrap still count as one row.
+    pub fn row_count(&self) -> usize {
+        self.dimensions.num_lines
+    }
+
+    /// Returns the index of the column at the given offset from the start of
+    /// each line, such as where the user clicked on the header, or `None` if
+    /// the offset falls between two columns or past the last one.
+    pub fn column_at(&self, offset: Width) -> Option<usize> {
+        let separator_width = self.table.filling.width();
+        let mut start = 0;
+        for (x, &width) in self.dimensions.widths.iter().enumerate() {
+            if offset < start {
+                return None;
+            }
+            else if offset < start + width {
+                return Some(x);
+            }
+
+            start += width + separator_width;
+        }
+
+        None
+    }
+
+    /// Splits a cell into the lines it takes up when displayed in the given
+    /// column, shortening or wrapping it if it’s too wide.
+    fn cell_lines(&self, cell: &Cell, x: usize) -> Vec<(String, Width)> {
+        let width = self.dimensions.widths[x];
+        if cell.width <= width {
+            return vec![ (cell.contents.to_string(), cell.width) ];
+        }
+
+        let column = self.table.column(x);
+        match column.overflow {
+            Overflow::Truncate  => vec![ truncate_string(&cell.contents, width, column.truncate_side, self.table.emoji_width) ],
+            Overflow::Wrap      => wrap_string(&cell.contents, width, self.table.emoji_width),
+        }
+    }
+}
+
+impl fmt::Display for TableDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.table.all_rows() {
+            let lines: Vec<_> = row.iter().enumerate()
+                                   .map(|(x, cell)| self.cell_lines(cell, x))
+                                   .collect();
+            let cells: Vec<Option<&Cell>> = row.iter().map(Some).collect();
+            let height = lines.iter().map(Vec::len).max().unwrap_or(1);
+
+            for line in 0 .. height {
+
+                // Stop after the last column with anything in it, so lines
+                // don’t end in padding.
+                let Some(last) = (0 .. lines.len()).rposition(|x| line_of(&cells, &lines, x, line, height).is_some()) else {
+                    writeln!(f)?;
+                    continue;
+                };
+
+                for (x, cell) in row.iter().enumerate().take(last + 1) {
+                    let (contents, width) = match line_of(&cells, &lines, x, line, height) {
+                        Some((contents, width)) => (&contents[..], *width),
+                        None                    => ("", 0),
+                    };
+
+                    let alignment = self.table.column(x).alignment.unwrap_or(cell.alignment);
+                    let pad_char = self.table.column(x).pad_char.unwrap_or(cell.pad_char);
+                    let padding = self.dimensions.widths[x].saturating_sub(width);
+
+                    if x == last {
+                        write!(f, "{}", pad_string_trimmed(contents, padding, alignment, pad_char))?;
+                        break;
+                    }
+
+                    write!(f, "{}", pad_string(contents, padding, alignment, pad_char))?;
+
+                    // Separators are only drawn on the first line of a row.
+                    match &self.table.filling {
+                        Filling::Text(text) if line == 0  => write!(f, "{}", text)?,
+                        filling                           => write!(f, "{}", spaces(filling.width()))?,
+                    }
+                }
+
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::VerticalAlignment;
+
+    fn planets() -> Table {
+        let mut table = Table::new(Filling::Text(" | ".into()));
+        table.set_headers(["planet", "moons", "notes"]);
+        table.add_row(["Mercury", "0", "closest to the sun"]);
+        table.add_row(["Jupiter", "95", "largest planet"]);
+        table.set_alignment(1, Alignment::Right);
+        table
+    }
+
+    #[test]
+    fn natural_width() {
+        let table = planets();
+        let display = table.fit_into_width(80).unwrap();
+
+        assert_eq!(display.width(), 7 + 3 + 5 + 3 + 18);
+        assert_eq!(display.row_count(), 3);
+        assert_eq!(display.to_string(), "\
+            planet  | moons | notes\n\
+            Mercury |     0 | closest to the sun\n\
+            Jupiter |    95 | largest planet\n");
+    }
+
+    #[test]
+    fn truncated() {
+        let table = planets();
+        let display = table.fit_into_width(30).unwrap();
+
+        assert_eq!(display.width(), 30);
+        assert_eq!(display.to_string(), "\
+            planet  | moons | notes\n\
+            Mercury |     0 | closest to …\n\
+            Jupiter |    95 | largest pla…\n");
+    }
+
+    #[test]
+    fn truncated_in_the_middle() {
+        let mut table = planets();
+        table.set_truncate_side(2, TruncateSide::Middle);
+        let display = table.fit_into_width(30).unwrap();
+
+        assert_eq!(display.to_string(), "\
+            planet  | moons | notes\n\
+            Mercury |     0 | closes…e sun\n\
+            Jupiter |    95 | larges…lanet\n");
+    }
+
+    #[test]
+    fn wrapped() {
+        let mut table = planets();
+        table.set_overflow(2, Overflow::Wrap);
+        let display = table.fit_into_width(30).unwrap();
+
+        assert_eq!(display.row_count(), 3);
+        let lines: Vec<String> = display.to_string().lines().map(String::from).collect();
+        assert_eq!(lines, vec![
+            "planet  | moons | notes".to_string(),
+            "Mercury |     0 | closest to".into(),
+            format!("{}the sun", spaces(18)),
+            "Jupiter |    95 | largest".into(),
+            format!("{}planet", spaces(18)),
+        ]);
+    }
+
+    #[test]
+    fn vertically_aligned() {
+        let mut table = planets();
+        table.set_overflow(2, Overflow::Wrap);
+        table.rows[0][0].vertical_alignment = VerticalAlignment::Bottom;
+        table.rows[0][1].vertical_alignment = VerticalAlignment::Middle;
+        let display = table.fit_into_width(27).unwrap();
+
+        let lines: Vec<String> = display.to_string().lines().map(String::from).collect();
+        assert_eq!(lines, vec![
+            "planet  | moons | notes".to_string(),
+            "        |       | closest".into(),
+            "              0   to the".into(),
+            "Mercury           sun".into(),
+            "Jupiter |    95 | largest".into(),
+            format!("{}planet", spaces(18)),
+        ]);
+    }
+
+    #[test]
+    fn leaders() {
+        let mut table = Table::new(Filling::Spaces(1));
+        table.add_row(["chapter one", "1"]);
+        table.add_row(["two", "15"]);
+        table.set_alignment(1, Alignment::Right);
+        table.set_pad_char(0, '.');
+
+        let display = table.fit_into_width(80).unwrap();
+        assert_eq!(display.to_string(), "chapter one  1\ntwo........ 15\n");
+    }
+
+    #[test]
+    fn too_narrow() {
+        let table = planets();
+        assert_eq!(table.fit_into_width(8), None);
+    }
+
+    #[test]
+    fn sort_indicators() {
+        let mut table = planets();
+        table.set_sort_order(1, Some(SortOrder::Descending));
+        assert_eq!(table.sort_order(1), Some(SortOrder::Descending));
+        assert_eq!(table.sort_order(0), None);
+
+        let display = table.fit_into_width(80).unwrap();
+        assert_eq!(display.to_string(), "\
+            planet  | moons ▼ | notes\n\
+            Mercury |       0 | closest to the sun\n\
+            Jupiter |      95 | largest planet\n");
+
+        table.set_sort_order(1, None);
+        assert_eq!(table.fit_into_width(80).unwrap().to_string(), planets().fit_into_width(80).unwrap().to_string());
+    }
+
+    #[test]
+    fn column_at_offset() {
+        let table = planets();
+        let display = table.fit_into_width(80).unwrap();
+
+        assert_eq!(display.column_at(0), Some(0));
+        assert_eq!(display.column_at(6), Some(0));
+        assert_eq!(display.column_at(8), None);
+        assert_eq!(display.column_at(10), Some(1));
+        assert_eq!(display.column_at(18), Some(2));
+        assert_eq!(display.column_at(36), None);
+    }
+
+    #[test]
+    fn wrapped_cell_wider_than_its_column() {
+        let mut table = Table::new(Filling::Spaces(1));
+        table.add_row([ "日本語", "abc" ]);
+        table.set_overflow(0, Overflow::Wrap);
+
+        // Wrapping can’t split a character, so the first column can’t get
+        // any narrower than two.
+        assert_eq!(table.fit_into_width(3), None);
+
+        let display = table.fit_into_width(4).unwrap();
+        assert_eq!(display.width(), 4);
+        assert_eq!(display.to_string(), "日 …\n本\n語\n");
+    }
+
+    #[test]
+    fn wide_emoji() {
+        let toned = "a\u{1F3FD}";
+        let mut table = Table::new(Filling::Spaces(1));
+        table.add_row([ toned, "x" ]);
+        assert_eq!(table.fit_into_width(80).unwrap().width(), 5);
+
+        // Cells are measured again when the policy changes, as well as
+        // when they’re added afterwards.
+        table.set_emoji_width(EmojiWidth::Wide);
+        table.add_row([ "ab", toned ]);
+
+        let display = table.fit_into_width(5).unwrap();
+        assert_eq!(display.width(), 5);
+        assert_eq!(display.to_string(), format!("{t} x\nab {t}\n", t = toned));
+    }
+}
@@ -0,0 +1,69 @@
+//! Bindings for JavaScript, so web terminals and playgrounds can lay out
+//! grids the same way. The grid is exposed to JavaScript as `Grid`, with
+//! only the basics: cells go in as strings, and layouts come out as either
+//! rendered text or JSON.
+
+use wasm_bindgen::prelude::*;
+
+use super::{Direction, Filling, Grid, GridOptions};
+
+
+/// A grid of strings, called `Grid` on the JavaScript side.
+#[wasm_bindgen(js_name = Grid)]
+#[derive(Debug)]
+pub struct WasmGrid {
+    grid: Grid,
+}
+
+#[wasm_bindgen(js_class = Grid)]
+impl WasmGrid {
+
+    /// Creates an empty grid that puts the given separator between its
+    /// columns, filling them from top to bottom if asked, or from left to
+    /// right otherwise.
+    #[wasm_bindgen(constructor)]
+    pub fn new(separator: &str, top_to_bottom: bool) -> Self {
+        let direction = if top_to_bottom { Direction::TopToBottom } else { Direction::LeftToRight };
+        let options = GridOptions::new(Filling::Text(separator.into()), direction);
+        Self { grid: Grid::new(options) }
+    }
+
+    /// Adds a cell to the end of the grid.
+    pub fn add(&mut self, contents: &str) {
+        self.grid.add(contents.into());
+    }
+
+    /// Renders the grid fitted into the given width, or returns `undefined`
+    /// if it doesn’t fit.
+    pub fn render(&self, width: usize) -> Option<String> {
+        self.grid.fit_into_width(width).map(|display| display.to_string())
+    }
+
+    /// Describes the layout of the grid fitted into the given width as JSON,
+    /// in the form given by `Display::to_json`, or returns `undefined` if it
+    /// doesn’t fit.
+    #[wasm_bindgen(js_name = layoutJson)]
+    pub fn layout_json(&self, width: usize) -> Option<String> {
+        self.grid.fit_into_width(width).map(|display| display.to_json().to_string())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_and_describe() {
+        let mut grid = WasmGrid::new("  ", false);
+        for s in ["one", "two", "three"] {
+            grid.add(s);
+        }
+
+        assert_eq!(grid.render(80).as_deref(), Some("one  two  three\n"));
+        assert_eq!(grid.render(2), None);
+
+        let json: serde_json::Value = serde_json::from_str(&grid.layout_json(80).unwrap()).unwrap();
+        assert_eq!(json["column_widths"], serde_json::json!([ 3, 3, 5 ]));
+    }
+}
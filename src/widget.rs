@@ -0,0 +1,124 @@
+//! A widget for drawing grids in terminal UIs built with `ratatui`.
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui::widgets::Widget;
+
+use super::{Cell, Display};
+
+
+/// A function that picks the style for a cell, given its index and contents.
+type CellStyle<'a> = Box<dyn Fn(usize, &Cell) -> Style + 'a>;
+
+/// Draws a fitted grid into a `ratatui` buffer. Lines and columns that don’t
+/// fit into the widget’s area are clipped.
+pub struct GridWidget<'a, 'grid> {
+    display: &'a Display<'grid>,
+    style: Style,
+    cell_style: Option<CellStyle<'a>>,
+}
+
+impl<'a, 'grid> GridWidget<'a, 'grid> {
+    pub fn new(display: &'a Display<'grid>) -> Self {
+        Self { display, style: Style::default(), cell_style: None }
+    }
+
+    /// Sets the style of the whole area the widget is drawn into.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Styles each cell using the given function, which gets passed the
+    /// cell’s index in the grid along with the cell itself. The style covers
+    /// the cell’s padding as well as its contents, and is applied on top of
+    /// the widget’s own style.
+    pub fn cell_style(mut self, cell_style: impl Fn(usize, &Cell) -> Style + 'a) -> Self {
+        self.cell_style = Some(Box::new(cell_style));
+        self
+    }
+}
+
+impl Widget for GridWidget<'_, '_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, self.style);
+
+        let rendered = self.display.to_string();
+        for (line_number, line) in rendered.lines().enumerate().take(area.height.into()) {
+            buf.set_stringn(area.x, area.y + line_number as u16, line, area.width.into(), Style::default());
+        }
+
+        let Some(cell_style) = self.cell_style else { return };
+        for cell_area in self.display.cell_areas() {
+            let (Ok(x), Ok(y)) = (u16::try_from(cell_area.x), u16::try_from(cell_area.y)) else { continue };
+            if x >= area.width || y >= area.height {
+                continue;
+            }
+
+            let cell = &self.display.grid.cells[cell_area.index];
+            let rect = Rect {
+                x:      area.x + x,
+                y:      area.y + y,
+                width:  (area.width - x).min(cell_area.width.try_into().unwrap_or(u16::MAX)),
+                height: (area.height - y).min(cell_area.height.try_into().unwrap_or(u16::MAX)),
+            };
+            buf.set_style(rect, cell_style(cell_area.index, cell));
+        }
+    }
+}
+
+impl Widget for &Display<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        GridWidget::new(self).render(area, buf);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Direction, Filling, Grid, GridOptions};
+    use ratatui::style::Color;
+
+    fn grid() -> Grid {
+        let mut grid = Grid::new(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+        for s in &["one", "two", "three", "four", "five", "six", "seven",
+                   "eight", "nine", "ten", "eleven", "twelve", "thirteen"]
+        {
+            grid.add(Cell::from(*s));
+        }
+        grid
+    }
+
+    #[test]
+    fn clipped_to_area() {
+        let grid = grid();
+        let display = grid.fit_into_width(33).unwrap();
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 16, 2));
+        (&display).render(buf.area, &mut buf);
+
+        assert_eq!(buf, Buffer::with_lines([
+            "one    two    th",
+            "six    seven  ei",
+        ]));
+    }
+
+    #[test]
+    fn styled_cells() {
+        let grid = grid();
+        let display = grid.fit_into_width(33).unwrap();
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 33, 3));
+        GridWidget::new(&display)
+            .cell_style(|index, _| if index == 1 { Style::new().fg(Color::Red) } else { Style::new() })
+            .render(buf.area, &mut buf);
+
+        assert_eq!(buf[(6, 0)].fg, Color::Reset);
+        assert_eq!(buf[(7, 0)].fg, Color::Red);
+        assert_eq!(buf[(12, 0)].fg, Color::Red);
+        assert_eq!(buf[(14, 0)].fg, Color::Reset);
+        assert_eq!(buf[(7, 1)].fg, Color::Reset);
+    }
+}
@@ -0,0 +1,67 @@
+//! End-to-end tests for the `rc_column` binary, feeding it lines on
+//! standard input and checking what it writes out.
+
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rc_column"))
+        .args(args)
+        .env_remove("COLUMNS")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Bad options are turned away without reading anything, which can
+    // close the pipe before all of the input has been written.
+    if let Err(error) = child.stdin.take().unwrap().write_all(input.as_bytes()) {
+        assert_eq!(error.kind(), ErrorKind::BrokenPipe);
+    }
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(args: &[&str], input: &str) -> String {
+    let output = run(args, input);
+    assert!(output.status.success(), "rc_column failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const WORDS: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\ntwelve\n";
+
+#[test]
+fn columns() {
+    assert_eq!(stdout(&[ "-w", "27" ], WORDS), "\
+one    four  seven  ten
+two    five  eight  eleven
+three  six   nine   twelve
+");
+}
+
+#[test]
+fn lines_that_dont_fit() {
+    assert_eq!(stdout(&[ "-w", "5" ], "averyverylongline\nb\n"), "averyverylongline\nb\n");
+}
+
+#[test]
+fn table() {
+    let input = "name size\nREADME.md 1024\nsrc 4096\n";
+    let expected = "name       size\nREADME.md  1024\nsrc        4096\n";
+    assert_eq!(stdout(&[ "-t" ], input), expected);
+
+    // Tables too wide for the width still get their fields lined up.
+    assert_eq!(stdout(&[ "-t", "-w", "5" ], input), expected);
+}
+
+#[test]
+fn table_with_delimiters() {
+    assert_eq!(stdout(&[ "-t", "-d", ",", "-s", " | " ], "a,b\nccc,d\n"), "a   | b\nccc | d\n");
+}
+
+#[test]
+fn delimiters_without_table() {
+    let output = run(&[ "-d", "," ], "a,b\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("rc_column: --delimiters only works with --table\n"));
+}
@@ -0,0 +1,99 @@
+//! Golden tests for `Display::render_stable`, whose output is meant to stay
+//! the same between patch releases. Each case is rendered and compared byte
+//! for byte with the file of the same name in `tests/golden`.
+//!
+//! Run with `RC_COLUMN_BLESS=1` to write the files out again after a change
+//! to the output that’s meant to happen, and check the differences in
+//! before committing them.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use rc_column::{Alignment, Cell, Direction, Filling, Grid, GridOptions, IndexColumn, Overflow};
+
+const WORDS: [&str; 12] = [
+    "one", "two", "three", "four", "five", "six",
+    "seven", "eight", "nine", "ten", "eleven", "twelve",
+];
+
+fn grid(options: GridOptions) -> Grid {
+    let mut grid = Grid::new(options);
+    for word in WORDS {
+        grid.add(Cell::from(word));
+    }
+    grid
+}
+
+fn check(name: &str, rendered: String) {
+    let path: PathBuf = [ env!("CARGO_MANIFEST_DIR"), "tests", "golden", &format!("{}.txt", name) ].iter().collect();
+
+    if env::var_os("RC_COLUMN_BLESS").is_some() {
+        fs::write(&path, &rendered).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| panic!("can’t read {}: {}", path.display(), e));
+    assert!(rendered == expected, "output for {} has changed:\n--- expected\n{}--- rendered\n{}", name, expected, rendered);
+}
+
+#[test]
+fn top_to_bottom() {
+    let grid = grid(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+    check("top_to_bottom", grid.fit_into_width(27).unwrap().render_stable());
+}
+
+#[test]
+fn left_to_right() {
+    let grid = grid(GridOptions::new(Filling::Spaces(1), Direction::LeftToRight));
+    check("left_to_right", grid.fit_into_width(24).unwrap().render_stable());
+}
+
+#[test]
+fn text_separator() {
+    let grid = grid(GridOptions::new(Filling::Text(" | ".into()), Direction::TopToBottom));
+    check("text_separator", grid.fit_into_width(30).unwrap().render_stable());
+}
+
+#[test]
+fn right_aligned_numbers() {
+    let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::LeftToRight));
+    for number in [ 666666, 1, 55555, 22, 4444, 333, 88, 7 ] {
+        let mut cell = Cell::from(number.to_string());
+        cell.alignment = Alignment::Right;
+        grid.add(cell);
+    }
+    check("right_aligned_numbers", grid.fit_into_width(23).unwrap().render_stable());
+}
+
+#[test]
+fn groups_and_index() {
+    let mut grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom)
+        .index_column(IndexColumn::OneBased));
+    grid.start_group("odd:");
+    for word in [ "one", "three", "five", "seven" ] {
+        grid.add(Cell::from(word));
+    }
+    grid.start_group("even:");
+    for word in [ "two", "four", "six" ] {
+        grid.add(Cell::from(word));
+    }
+    check("groups_and_index", grid.fit_into_width(20).unwrap().render_stable());
+}
+
+#[test]
+fn wrapped_cells() {
+    let grid = Grid::from_rows(GridOptions::new(Filling::Text("|".into()), Direction::LeftToRight)
+        .max_column_width(8)
+        .overflow(Overflow::Wrap), vec![
+        vec![ "name", "description" ],
+        vec![ "rc_column", "formats strings into a grid" ],
+    ]);
+    check("wrapped_cells", grid.fit_into_width(20).unwrap().render_stable());
+}
+
+#[test]
+fn empty() {
+    let grid = Grid::new(GridOptions::new(Filling::Spaces(2), Direction::TopToBottom));
+    assert_eq!(grid.fit_into_width(10).unwrap().render_stable(), "");
+}